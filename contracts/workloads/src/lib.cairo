@@ -0,0 +1,109 @@
+//! Synthetic workloads used to isolate parts of the sequencer execution pipeline,
+//! every entrypoint repeats a single kind of work `amount` times.
+
+#[starknet::interface]
+trait IWorkloads<TContractState> {
+    fn compute(ref self: TContractState, amount: u32) -> felt252;
+    fn write_storage(ref self: TContractState, amount: u32);
+    fn emit_events(ref self: TContractState, amount: u32);
+    fn hash_poseidon(ref self: TContractState, amount: u32) -> felt252;
+    fn hash_pedersen(ref self: TContractState, amount: u32) -> felt252;
+}
+
+#[starknet::contract]
+mod Workloads {
+    use core::hash::HashStateTrait;
+    use core::pedersen::pedersen;
+    use core::poseidon::PoseidonTrait;
+    use starknet::{ContractAddress, get_caller_address};
+
+    #[storage]
+    struct Storage {
+        // Next unused slot of each caller, so that every write touches a new slot
+        next_slot: LegacyMap<ContractAddress, u64>,
+        slots: LegacyMap<(ContractAddress, u64), felt252>,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    enum Event {
+        Ping: Ping,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    struct Ping {
+        #[key]
+        caller: ContractAddress,
+        index: u32,
+    }
+
+    #[abi(embed_v0)]
+    impl WorkloadsImpl of super::IWorkloads<ContractState> {
+        fn compute(ref self: ContractState, amount: u32) -> felt252 {
+            let mut acc: felt252 = 1;
+            let mut i: u32 = 0;
+            loop {
+                if i == amount {
+                    break;
+                }
+                acc = acc * 3 + i.into();
+                i += 1;
+            };
+            acc
+        }
+
+        fn write_storage(ref self: ContractState, amount: u32) {
+            let caller = get_caller_address();
+            let first = self.next_slot.read(caller);
+            let amount: u64 = amount.into();
+            let mut i: u64 = 0;
+            loop {
+                if i == amount {
+                    break;
+                }
+                // Writing zero to an empty slot would not change the state
+                self.slots.write((caller, first + i), (first + i + 1).into());
+                i += 1;
+            };
+            self.next_slot.write(caller, first + amount);
+        }
+
+        fn emit_events(ref self: ContractState, amount: u32) {
+            let caller = get_caller_address();
+            let mut index: u32 = 0;
+            loop {
+                if index == amount {
+                    break;
+                }
+                self.emit(Ping { caller, index });
+                index += 1;
+            };
+        }
+
+        fn hash_poseidon(ref self: ContractState, amount: u32) -> felt252 {
+            let mut digest: felt252 = 0;
+            let mut i: u32 = 0;
+            loop {
+                if i == amount {
+                    break;
+                }
+                digest = PoseidonTrait::new().update(digest).update(i.into()).finalize();
+                i += 1;
+            };
+            digest
+        }
+
+        fn hash_pedersen(ref self: ContractState, amount: u32) -> felt252 {
+            let mut digest: felt252 = 0;
+            let mut i: u32 = 0;
+            loop {
+                if i == amount {
+                    break;
+                }
+                digest = pedersen(digest, i.into());
+                i += 1;
+            };
+            digest
+        }
+    }
+}
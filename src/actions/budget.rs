@@ -0,0 +1,163 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use goose::goose::TransactionFunction;
+use starknet::{
+    core::types::{BlockId, Felt, MaybePendingBlockWithReceipts},
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+};
+
+use crate::utils::receipt_actual_fee;
+
+/// How often the fees of the blocks produced since the last charge are added up,
+/// so that the node is queried at a bounded rate whatever the amount of transactions
+const CHARGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stops the shooters from submitting transactions once the fees
+/// paid by the transactions included since it was created exceed the budget
+///
+/// Fees are added up per block, from the receipts of whole blocks,
+/// as the node is expected to only include the transactions of the benchmark
+pub struct Budget {
+    max_total_fee: Felt,
+    spent: Mutex<Felt>,
+    exceeded: AtomicBool,
+    rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+    /// First block whose fees are not charged yet, held while blocks are being charged
+    next_block: tokio::sync::Mutex<u64>,
+    last_charge: Mutex<Instant>,
+    /// Runs of the submission transaction that did not submit anything
+    skipped: AtomicUsize,
+}
+
+impl Budget {
+    pub async fn new(
+        rpc_client: Arc<JsonRpcClient<HttpTransport>>,
+        max_total_fee: Felt,
+    ) -> color_eyre::Result<Self> {
+        let next_block = rpc_client.block_number().await? + 1;
+
+        Ok(Self {
+            max_total_fee,
+            spent: Mutex::new(Felt::ZERO),
+            exceeded: AtomicBool::new(false),
+            rpc_client,
+            next_block: tokio::sync::Mutex::new(next_block),
+            last_charge: Mutex::new(Instant::now()),
+            skipped: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Fees paid by the transactions of the blocks charged so far
+    pub fn spent(&self) -> Felt {
+        *self.spent.lock().expect("Budget lock is never poisoned")
+    }
+
+    /// Runs of the submission transaction skipped since the last call, as the budget was exceeded
+    pub fn take_skipped(&self) -> usize {
+        self.skipped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Skips the runs of `transaction` once the budget is exceeded,
+    /// and charges the fees of the new blocks at most every [`CHARGE_INTERVAL`] otherwise
+    pub fn enforce(self: &Arc<Self>, transaction: TransactionFunction) -> TransactionFunction {
+        let budget = self.clone();
+
+        Arc::new(move |user| {
+            let (budget, transaction) = (budget.clone(), transaction.clone());
+
+            Box::pin(async move {
+                if budget.exceeded() {
+                    budget.skipped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+
+                let result = transaction(user).await;
+
+                if budget.charge_due() {
+                    // Another user is already charging the new blocks otherwise
+                    if let Ok(mut next_block) = budget.next_block.try_lock() {
+                        budget.charge(&mut next_block).await;
+                    }
+                }
+
+                result
+            })
+        })
+    }
+
+    /// Charge the fees of the blocks produced so far, once the shooter waited for its last transactions
+    pub async fn settle(&self) {
+        let mut next_block = self.next_block.lock().await;
+        self.charge(&mut next_block).await;
+    }
+
+    fn charge_due(&self) -> bool {
+        let mut last_charge = self
+            .last_charge
+            .lock()
+            .expect("Budget lock is never poisoned");
+
+        if last_charge.elapsed() < CHARGE_INTERVAL {
+            return false;
+        }
+
+        *last_charge = Instant::now();
+        true
+    }
+
+    /// Add up the fees of the blocks from `next_block` to the latest one
+    async fn charge(&self, next_block: &mut u64) {
+        let latest = match self.rpc_client.block_number().await {
+            Ok(latest) => latest,
+            Err(error) => {
+                tracing::warn!("Failed to get the latest block to charge its fees: {error}");
+                return;
+            }
+        };
+
+        while *next_block <= latest {
+            let block = match self
+                .rpc_client
+                .get_block_with_receipts(BlockId::Number(*next_block))
+                .await
+            {
+                Ok(MaybePendingBlockWithReceipts::Block(block)) => block,
+                Ok(MaybePendingBlockWithReceipts::PendingBlock(_)) => return,
+                Err(error) => {
+                    tracing::warn!("Failed to get the fees of block {next_block}: {error}");
+                    return;
+                }
+            };
+
+            let fees = block
+                .transactions
+                .iter()
+                .fold(Felt::ZERO, |fees, transaction| {
+                    fees + receipt_actual_fee(&transaction.receipt).amount
+                });
+
+            *next_block += 1;
+
+            let mut spent = self.spent.lock().expect("Budget lock is never poisoned");
+            *spent += fees;
+
+            if *spent > self.max_total_fee && !self.exceeded.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "Transactions paid {} in fees, over the budget of {}, stopping the attack",
+                    *spent,
+                    self.max_total_fee
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use color_eyre::eyre::bail;
+
+use crate::{
+    config::{ContractSourceConfig, GatlingConfig},
+    generators::parameters::unknown_placeholders,
+};
+
+use super::{goose::make_goose_config, ShooterKind};
+
+/// Validate the whole configuration without sending anything to the node,
+/// then log the amount of transactions and requests each benchmark will do
+pub fn check_config(config: &GatlingConfig) -> color_eyre::Result<()> {
+    let mut problems = Vec::new();
+
+    let setup = &config.setup;
+    let contracts = [
+        ("setup.erc20_contract", Some(&setup.erc20_contract)),
+        ("setup.erc721_contract", Some(&setup.erc721_contract)),
+        ("setup.account_contract", Some(&setup.account_contract)),
+        ("setup.workload_contract", setup.workload_contract.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(name, contract)| Some((name.to_owned(), contract?)))
+    .chain(config.run.shooters.iter().filter_map(|shooter| {
+        let contract = shooter.contract.as_ref()?;
+        Some((format!("shooter `{}` contract", shooter.name), contract))
+    }));
+
+    for (name, contract) in contracts {
+        match hash_contract(contract) {
+            Ok(hashes) => tracing::info!("{name}: {hashes}"),
+            Err(error) => problems.push(format!(
+                "{name} at {}: {error}",
+                contract.get_contract_path().display()
+            )),
+        }
+    }
+
+    if let Err(error) = config.deployer.validate() {
+        problems.push(error.to_string());
+    }
+
+    if config.run.concurrency == 0 {
+        problems.push("run.concurrency must be at least 1".to_owned());
+    }
+
+    for shooter in &config.run.shooters {
+        let Some(kind) = ShooterKind::find(&shooter.name) else {
+            problems.push(format!("Shooter `{}` not found", shooter.name));
+            continue;
+        };
+
+        if let Err(error) = shooter.calls_per_tx.validate() {
+            problems.push(format!("Shooter `{}`: {error}", shooter.name));
+        }
+
+        if let Err(error) = (kind.validate)(config, shooter) {
+            problems.push(format!("Shooter `{}`: {error}", shooter.name));
+        }
+
+        match shooter.batch_size {
+            Some(0) => problems.push(format!("Shooter `{}` has a batch size of 0", shooter.name)),
+            Some(_) if !kind.batchable => problems.push(format!(
+                "Shooter `{}` does not support batch submission",
+                shooter.name
+            )),
+            _ => {}
+        }
+
+        if shooter.shoot == 0 || config.run.concurrency == 0 {
+            tracing::info!("Shooter `{}` will be skipped", shooter.name);
+            continue;
+        }
+
+        let batch_size = shooter.batch_size.unwrap_or(1).max(1);
+        plan(
+            config,
+            &shooter.name,
+            "transactions",
+            shooter.shoot / batch_size,
+            batch_size,
+            &mut problems,
+        );
+    }
+
+    for bench in &config.run.read_benches {
+        match (&bench.parameters_location, &bench.parameters_generator) {
+            (Some(parameters), None) if parameters.is_empty() => problems.push(format!(
+                "Read bench `{}` has an empty parameters file",
+                bench.name
+            )),
+            (None, Some(generator)) => {
+                for placeholder in unknown_placeholders(&generator.template) {
+                    problems.push(format!(
+                        "Read bench `{}` uses unknown placeholder `{placeholder}`",
+                        bench.name
+                    ));
+                }
+            }
+            (Some(_), None) => {}
+            _ => problems.push(format!(
+                "Read bench `{}` needs exactly one of `parameters_location` and `parameters_generator`",
+                bench.name
+            )),
+        }
+
+        if bench.batch_size == Some(0) {
+            problems.push(format!("Read bench `{}` has a batch size of 0", bench.name));
+        }
+
+        if config.run.concurrency != 0 {
+            let batch_size = bench.batch_size.unwrap_or(1).max(1);
+            plan(
+                config,
+                &bench.name,
+                "requests",
+                bench.num_requests / batch_size,
+                batch_size,
+                &mut problems,
+            );
+        }
+    }
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            tracing::error!("{problem}");
+        }
+
+        bail!("Found {} problems in the configuration", problems.len());
+    }
+
+    tracing::info!("Configuration is valid");
+
+    Ok(())
+}
+
+/// Hashes of the artifacts of `contract`, reading them the same way declaring them does
+fn hash_contract(contract: &ContractSourceConfig) -> color_eyre::Result<String> {
+    let class_hash = contract.get_class_hash()?;
+
+    Ok(match contract.get_casm_hash()? {
+        Some(casm_hash) => format!("class hash {class_hash:#064x}, casm hash {casm_hash:#064x}"),
+        None => format!("class hash {class_hash:#064x}"),
+    })
+}
+
+/// Log what goose will actually do of the `iterations` asked for `name`,
+/// each iteration sending a batch of `batch_size`
+fn plan(
+    config: &GatlingConfig,
+    name: &str,
+    unit: &str,
+    iterations: u64,
+    batch_size: u64,
+    problems: &mut Vec<String>,
+) {
+    match make_goose_config(config, iterations, name) {
+        Ok(goose_config) => {
+            let per_user = goose_config.iterations as u64 * batch_size;
+
+            tracing::info!(
+                "{name}: {per_user} {unit} per user over {} users, {} in total",
+                config.run.concurrency,
+                per_user * config.run.concurrency
+            );
+        }
+        Err(error) => problems.push(error.to_string()),
+    }
+}
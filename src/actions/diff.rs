@@ -0,0 +1,349 @@
+use std::{sync::Arc, time::Instant};
+
+use color_eyre::eyre::{ensure, eyre, OptionExt};
+use serde_json::{json, Map, Value};
+use starknet::{
+    core::types::Felt,
+    providers::jsonrpc::{HttpTransport, JsonRpcMethod, JsonRpcResponse, JsonRpcTransport},
+};
+use tokio::task::JoinSet;
+use url::Url;
+
+use crate::{
+    config::{GatlingConfig, ReadBenchConfig},
+    generators::parameters::ReadParameters,
+    metrics::{BenchmarkReport, MetricResult},
+};
+
+use super::{goose::positional_params, ws::Latencies};
+
+/// How many mismatching requests are kept as examples in the report
+const MAX_EXAMPLES: usize = 5;
+
+/// How many differing fields are kept for each example
+const MAX_DIFFERENCES: usize = 10;
+
+/// What a concurrent user saw while comparing the two nodes
+#[derive(Debug, Default)]
+struct Comparison {
+    reference: Latencies,
+    tested: Latencies,
+    compared: u64,
+    mismatches: u64,
+    examples: Vec<Value>,
+}
+
+/// Send every request of the read bench to both the reference node and the node under test,
+/// comparing their normalized responses and latencies
+pub async fn diff_read_bench(
+    config: &GatlingConfig,
+    bench: &ReadBenchConfig,
+    parameters: ReadParameters,
+) -> color_eyre::Result<BenchmarkReport> {
+    let reference_url = config
+        .rpc
+        .reference_url
+        .as_ref()
+        .ok_or_eyre("diff-read needs `rpc.reference_url`")?;
+
+    let reference = Arc::new(HttpTransport::new(Url::parse(reference_url)?));
+    let tested = Arc::new(HttpTransport::new(Url::parse(&config.rpc.url)?));
+
+    let users = config.run.concurrency;
+    ensure!(
+        bench.num_requests >= users,
+        "Too few read calls for the amount of concurrent users"
+    );
+    let iterations = bench.num_requests / users;
+
+    let mut joinset = JoinSet::new();
+
+    for _ in 0..users {
+        let (reference, tested) = (reference.clone(), tested.clone());
+        let (parameters, ignored) = (parameters.clone(), bench.ignore_fields.clone());
+        let method = bench.method;
+
+        joinset.spawn(async move {
+            let mut comparison = Comparison::default();
+
+            for _ in 0..iterations {
+                let params = positional_params(parameters.sample());
+
+                let (reference_response, tested_response) = tokio::join!(
+                    timed_request(&reference, method, &params, &mut comparison.reference),
+                    timed_request(&tested, method, &params, &mut comparison.tested),
+                );
+
+                let (Some(reference_response), Some(tested_response)) =
+                    (reference_response, tested_response)
+                else {
+                    continue;
+                };
+
+                let reference_response = normalize(reference_response, &ignored);
+                let tested_response = normalize(tested_response, &ignored);
+
+                comparison.compared += 1;
+
+                if reference_response != tested_response {
+                    comparison.mismatches += 1;
+
+                    let mut differences = Vec::new();
+                    diff("", &reference_response, &tested_response, &mut differences);
+
+                    comparison.examples.push(json!({
+                        "params": params,
+                        "differences": differences,
+                    }));
+                    comparison.examples.truncate(MAX_EXAMPLES);
+                }
+            }
+
+            comparison
+        });
+    }
+
+    let mut comparison = Comparison::default();
+
+    while let Some(result) = joinset.join_next().await {
+        let user = result.map_err(|err| eyre!(err))?;
+
+        comparison.reference.times.extend(user.reference.times);
+        comparison.reference.failed += user.reference.failed;
+        comparison.tested.times.extend(user.tested.times);
+        comparison.tested.failed += user.tested.failed;
+        comparison.compared += user.compared;
+        comparison.mismatches += user.mismatches;
+        comparison.examples.extend(user.examples);
+    }
+
+    comparison.examples.truncate(MAX_EXAMPLES);
+
+    let mut report = BenchmarkReport::new(bench.name.clone(), comparison.compared as usize);
+
+    report.metrics.extend_from_slice(&[
+        MetricResult {
+            name: "Compared Responses".into(),
+            unit: "responses",
+            value: comparison.compared.into(),
+        },
+        MetricResult {
+            name: "Mismatches".into(),
+            unit: "responses",
+            value: comparison.mismatches.into(),
+        },
+    ]);
+
+    if !comparison.examples.is_empty() {
+        report.metrics.push(MetricResult {
+            name: "Mismatch Examples".into(),
+            unit: "",
+            value: Value::Array(comparison.examples),
+        });
+    }
+
+    report.with_latencies(
+        &mut comparison.reference.times,
+        comparison.reference.failed,
+        Some("Reference"),
+    );
+    report.with_latencies(
+        &mut comparison.tested.times,
+        comparison.tested.failed,
+        Some("Tested"),
+    );
+
+    Ok(report)
+}
+
+/// Send a request, recording its latency, and return the response as a comparable value
+async fn timed_request(
+    transport: &HttpTransport,
+    method: JsonRpcMethod,
+    params: &Value,
+    latencies: &mut Latencies,
+) -> Option<Value> {
+    let start = Instant::now();
+    let response = transport.send_request::<_, Value>(method, params).await;
+    let time = start.elapsed().as_millis() as u64;
+
+    match response {
+        Ok(JsonRpcResponse::Success { result, .. }) => {
+            latencies.times.push(time);
+            Some(json!({ "result": result }))
+        }
+        // Implementations word their errors differently, only the code is compared
+        Ok(JsonRpcResponse::Error { error, .. }) => {
+            latencies.times.push(time);
+            Some(json!({ "error": error.code }))
+        }
+        Err(error) => {
+            tracing::debug!("Request to compare failed: {error}");
+            latencies.failed += 1;
+            None
+        }
+    }
+}
+
+/// Remove the ignored and null fields and give felts a single representation,
+/// so that only meaningful differences remain
+fn normalize(value: Value, ignored: &[String]) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, value)| !value.is_null() && !ignored.contains(key))
+                .map(|(key, value)| (key, normalize(value, ignored)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .map(|value| normalize(value, ignored))
+                .collect(),
+        ),
+        Value::String(string) if string.starts_with("0x") => match Felt::from_hex(&string) {
+            Ok(felt) => Value::String(format!("{felt:#x}")),
+            Err(_) => Value::String(string),
+        },
+        value => value,
+    }
+}
+
+/// Collect the paths where `reference` and `tested` differ
+fn diff(path: &str, reference: &Value, tested: &Value, differences: &mut Vec<Value>) {
+    if differences.len() >= MAX_DIFFERENCES || reference == tested {
+        return;
+    }
+
+    match (reference, tested) {
+        (Value::Object(reference), Value::Object(tested)) => {
+            let keys = reference
+                .keys()
+                .chain(tested.keys().filter(|key| !reference.contains_key(*key)));
+
+            for key in keys {
+                let null = Value::Null;
+                diff(
+                    &format!("{path}.{key}"),
+                    reference.get(key).unwrap_or(&null),
+                    tested.get(key).unwrap_or(&null),
+                    differences,
+                );
+            }
+        }
+        (Value::Array(reference), Value::Array(tested)) if reference.len() == tested.len() => {
+            for (index, (reference, tested)) in reference.iter().zip(tested).enumerate() {
+                diff(&format!("{path}[{index}]"), reference, tested, differences);
+            }
+        }
+        _ => differences.push(json!({
+            "path": path,
+            "reference": reference,
+            "tested": tested,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Differences between `reference` and `tested` once both are normalized
+    fn differences(reference: Value, tested: Value, ignored: &[&str]) -> Vec<Value> {
+        let ignored: Vec<_> = ignored.iter().map(|field| field.to_string()).collect();
+
+        let mut differences = Vec::new();
+        diff(
+            "",
+            &normalize(reference, &ignored),
+            &normalize(tested, &ignored),
+            &mut differences,
+        );
+        differences
+    }
+
+    #[test]
+    fn field_ordering() {
+        let reference = json!({
+            "result": { "block_number": 1, "status": "ACCEPTED_ON_L2", "transactions": ["0x1"] }
+        });
+        let tested = json!({
+            "result": { "transactions": ["0x1"], "status": "ACCEPTED_ON_L2", "block_number": 1 }
+        });
+
+        assert_eq!(differences(reference, tested, &[]), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn ignored_null_and_felt_representations() {
+        let reference = json!({
+            "result": {
+                "block_hash": "0x1",
+                "timestamp": 100,
+                "l1_gas_price": { "price_in_fri": null, "price_in_wei": "0x0a" },
+            }
+        });
+        let tested = json!({
+            "result": {
+                "block_hash": "0x00001",
+                "timestamp": 200,
+                "l1_gas_price": { "price_in_wei": "0xA" },
+            }
+        });
+
+        assert_eq!(
+            differences(reference, tested, &["timestamp"]),
+            Vec::<Value>::new()
+        );
+    }
+
+    #[test]
+    fn mismatches() {
+        let reference = json!({
+            "result": {
+                "status": "ACCEPTED_ON_L2",
+                "events": [{ "keys": ["0x1"] }, { "keys": ["0x2"] }],
+                "transactions": ["0x1", "0x2"],
+            }
+        });
+        let tested = json!({
+            "result": {
+                "status": "PENDING",
+                "events": [{ "keys": ["0x1"] }, { "keys": ["0x3"] }],
+                "transactions": ["0x1"],
+                "extra": true,
+            }
+        });
+
+        assert_eq!(
+            differences(reference, tested, &[]),
+            [
+                json!({
+                    "path": ".result.status",
+                    "reference": "ACCEPTED_ON_L2",
+                    "tested": "PENDING",
+                }),
+                json!({
+                    "path": ".result.events[1].keys[0]",
+                    "reference": "0x2",
+                    "tested": "0x3",
+                }),
+                json!({
+                    "path": ".result.transactions",
+                    "reference": ["0x1", "0x2"],
+                    "tested": ["0x1"],
+                }),
+                json!({ "path": ".result.extra", "reference": null, "tested": true }),
+            ]
+        );
+    }
+
+    #[test]
+    fn differences_are_capped() {
+        let reference = Value::Array((0..20).map(Value::from).collect());
+        let tested = Value::Array((100..120).map(Value::from).collect());
+
+        assert_eq!(differences(reference, tested, &[]).len(), MAX_DIFFERENCES);
+    }
+}
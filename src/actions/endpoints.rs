@@ -0,0 +1,174 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use color_eyre::eyre::{eyre, WrapErr};
+use goose::goose::{GooseUser, TransactionFunction};
+use rand::distributions::{Distribution, WeightedIndex};
+use url::Url;
+
+use crate::config::{EndpointStrategy, RpcConfig};
+
+/// Spreads the goose requests over the configured endpoints
+///
+/// The endpoints become the base url of the goose users as a whole, requests being
+/// posted to [`RPC_PATH`](super::goose::RPC_PATH) so that paths such as `/rpc/v0_7` are kept
+pub struct Endpoints {
+    urls: Vec<Url>,
+    strategy: EndpointStrategy,
+    weights: WeightedIndex<u64>,
+    /// Index of the next endpoint with the round robin strategy
+    next: AtomicUsize,
+    verification_url: Url,
+}
+
+impl Endpoints {
+    pub fn from_config(config: &RpcConfig) -> color_eyre::Result<Self> {
+        let (urls, weights): (Vec<_>, Vec<_>) = if config.endpoints.is_empty() {
+            (vec![config.url.clone()], vec![1])
+        } else {
+            config
+                .endpoints
+                .iter()
+                .map(|endpoint| (endpoint.url.clone(), endpoint.weight))
+                .unzip()
+        };
+
+        let urls = urls
+            .iter()
+            .map(|url| Url::parse(url).wrap_err_with(|| format!("Invalid endpoint url {url}")))
+            .collect::<color_eyre::Result<_>>()?;
+
+        let weights =
+            WeightedIndex::new(weights).map_err(|err| eyre!("Invalid endpoint weights: {err}"))?;
+
+        Ok(Self {
+            urls,
+            strategy: config.strategy,
+            weights,
+            next: AtomicUsize::new(0),
+            verification_url: Url::parse(config.verification_url())?,
+        })
+    }
+
+    /// Endpoint the next request of `user` is sent to
+    fn pick(&self, user: &GooseUser) -> &Url {
+        let index = match self.strategy {
+            EndpointStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            EndpointStrategy::Pinned => user.weighted_users_index,
+            EndpointStrategy::Weighted => self.weights.sample(&mut rand::thread_rng()),
+        };
+
+        &self.urls[index % self.urls.len()]
+    }
+
+    /// Sends the requests of each run of `transaction` to the endpoint picked for it
+    pub fn distribute(self: &Arc<Self>, transaction: TransactionFunction) -> TransactionFunction {
+        let endpoints = self.clone();
+
+        Arc::new(move |user| {
+            user.base_url = endpoints.pick(user).clone();
+
+            transaction(user)
+        })
+    }
+
+    /// Sends the requests of `transaction` to the verification endpoint
+    pub fn verify(self: &Arc<Self>, transaction: TransactionFunction) -> TransactionFunction {
+        let endpoints = self.clone();
+
+        Arc::new(move |user| {
+            user.base_url = endpoints.verification_url.clone();
+
+            transaction(user)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use goose::config::GooseConfiguration;
+
+    use crate::{actions::goose::RPC_PATH, config::EndpointConfig};
+
+    use super::*;
+
+    /// Urls the requests of successive runs of a transaction wrapped by `wrap` are sent to
+    async fn request_urls(
+        wrap: impl Fn(TransactionFunction) -> TransactionFunction,
+        runs: usize,
+    ) -> Vec<String> {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = urls.clone();
+
+        let transaction = wrap(Arc::new(move |user| {
+            let url = user.build_url(RPC_PATH).unwrap();
+            recorded.lock().unwrap().push(url);
+            Box::pin(async { Ok(()) })
+        }));
+
+        let mut user = GooseUser::single(
+            Url::parse("http://localhost:9944").unwrap(),
+            &GooseConfiguration::default(),
+        )
+        .unwrap();
+
+        for _ in 0..runs {
+            transaction(&mut user).await.unwrap();
+        }
+
+        let urls = urls.lock().unwrap().clone();
+        urls
+    }
+
+    #[tokio::test]
+    async fn endpoints_keep_their_path() {
+        let endpoints = Arc::new(
+            Endpoints::from_config(&RpcConfig {
+                endpoints: vec![
+                    EndpointConfig {
+                        url: "http://balancer:8080/route/a".into(),
+                        weight: 1,
+                    },
+                    EndpointConfig {
+                        url: "http://node:9944/rpc/v0_7".into(),
+                        weight: 1,
+                    },
+                ],
+                verification_url: Some("http://verifier:9944/rpc/v0_7".into()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+
+        assert_eq!(
+            request_urls(|transaction| endpoints.distribute(transaction), 3).await,
+            [
+                "http://balancer:8080/route/a",
+                "http://node:9944/rpc/v0_7",
+                "http://balancer:8080/route/a",
+            ]
+        );
+
+        assert_eq!(
+            request_urls(|transaction| endpoints.verify(transaction), 1).await,
+            ["http://verifier:9944/rpc/v0_7"]
+        );
+    }
+
+    #[test]
+    fn invalid_endpoint_url() {
+        let config = RpcConfig {
+            endpoints: vec![EndpointConfig {
+                url: "not a url".into(),
+                weight: 1,
+            }],
+            ..Default::default()
+        };
+
+        assert!(Endpoints::from_config(&config).is_err());
+    }
+}
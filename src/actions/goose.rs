@@ -2,15 +2,18 @@ use std::{
     mem,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex, MutexGuard,
     },
     time::{Duration, SystemTime},
 };
 
 use color_eyre::eyre::ensure;
 use crossbeam_queue::ArrayQueue;
-use goose::{config::GooseConfiguration, metrics::GooseRequestMetric, prelude::*};
-use rand::prelude::SliceRandom;
+use goose::{
+    config::GooseConfiguration,
+    metrics::{GooseRawRequest, GooseRequestMetric},
+    prelude::*,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use starknet::{
     accounts::RawExecutionV1,
@@ -19,28 +22,30 @@ use starknet::{
     },
 };
 use starknet::{
-    accounts::{Account, ConnectedAccount, ExecutionEncoder, SingleOwnerAccount},
+    accounts::{Account, ConnectedAccount, ExecutionEncoder},
     core::types::{
         BroadcastedInvokeTransaction, BroadcastedInvokeTransactionV1, ExecutionResult, Felt,
     },
     providers::{
-        jsonrpc::{HttpTransport, JsonRpcError, JsonRpcMethod, JsonRpcResponse},
-        JsonRpcClient, ProviderError,
+        jsonrpc::{JsonRpcError, JsonRpcMethod, JsonRpcResponse},
+        ProviderError,
     },
-    signers::LocalWallet,
 };
 
 use crate::{
-    actions::setup::{GatlingSetup, CHECK_INTERVAL, MAX_FEE},
-    config::{GatlingConfig, ParametersFile},
+    actions::setup::{CHECK_INTERVAL, MAX_FEE},
+    config::GatlingConfig,
+    generators::parameters::ReadParameters,
+    metrics::BatchStats,
+    spec,
 };
 
-use super::setup::StarknetAccount;
+use super::{endpoints::Endpoints, setup::StarknetAccount};
 
 pub fn make_goose_config(
     config: &GatlingConfig,
     amount: u64,
-    name: &'static str,
+    name: &str,
 ) -> color_eyre::Result<GooseConfiguration> {
     ensure!(
         amount >= config.run.concurrency,
@@ -110,7 +115,15 @@ pub async fn setup(
     }))
 }
 
-pub fn goose_write_user_wait_last_tx() -> TransactionFunction {
+/// Transactions submitted so far by a write user
+pub fn submitted_transactions(user: &GooseUser) -> &[Felt] {
+    &user
+        .get_session_data::<GooseWriteUserState>()
+        .expect("Should be in a goose user with GooseUserState session data")
+        .prev_tx
+}
+
+pub fn goose_write_user_wait_last_tx(accept_reverted: bool) -> TransactionFunction {
     Arc::new(move |user| {
         let tx = user
             .get_session_data::<GooseWriteUserState>()
@@ -122,7 +135,7 @@ pub fn goose_write_user_wait_last_tx() -> TransactionFunction {
         Box::pin(async move {
             // If all transactions failed, we can skip this step
             if let Some(tx) = tx {
-                wait_for_tx_with_goose(user, tx).await?;
+                wait_for_tx_with_goose(user, tx, accept_reverted).await?;
             }
 
             Ok(())
@@ -131,37 +144,45 @@ pub fn goose_write_user_wait_last_tx() -> TransactionFunction {
 }
 
 pub async fn read_method(
-    shooter: &GatlingSetup,
+    config: &GatlingConfig,
     amount: u64,
     method: JsonRpcMethod,
-    parameters_list: ParametersFile,
+    parameters: ReadParameters,
+    batch: Option<Arc<Batch>>,
 ) -> color_eyre::Result<GooseMetrics> {
-    let goose_read_config = make_goose_config(shooter.config(), amount, "read calls")?;
+    let batch_size = batch.as_ref().map_or(1, |batch| batch.size);
+    let goose_read_config = make_goose_config(config, amount / batch_size, "read calls")?;
+    let endpoints = Arc::new(Endpoints::from_config(&config.rpc)?);
 
     let reads: TransactionFunction = Arc::new(move |user| {
-        let mut rng = rand::thread_rng();
-
-        let mut params_list = parameters_list.clone();
-        params_list.shuffle(&mut rng); // Make sure each goose user has their own order
-        let mut paramaters_cycle = params_list.into_iter().cycle();
+        // Each request gets its own parameters so goose users do not all hit the same data
+        let params: Vec<_> = (0..batch_size)
+            .map(|_| positional_params(parameters.sample()))
+            .collect();
+        let batch = batch.clone();
 
         Box::pin(async move {
-            let params = paramaters_cycle
-                .next()
-                .expect("Cyclic iterator should never end");
+            match batch {
+                Some(batch) => {
+                    let (responses, metric) =
+                        raw_send_batch::<serde_json::Value>(user, method, params).await?;
 
-            let _: (serde_json::Value, _) =
-                send_request(user, method, serde_json::Value::Object(params)).await?;
+                    batch.record(metric.response_time, &responses);
+                }
+                None => {
+                    let _: (serde_json::Value, _) =
+                        send_request_params(user, method, &params[0]).await?;
+                }
+            }
 
             Ok(())
         })
     });
 
     let metrics = GooseAttack::initialize_with_config(goose_read_config)?
-        .register_scenario(
-            scenario!("Read Metric")
-                .register_transaction(Transaction::new(reads).set_name("Request")),
-        )
+        .register_scenario(scenario!("Read Metric").register_transaction(
+            Transaction::new(endpoints.distribute(reads)).set_name("Request"),
+        ))
         .execute()
         .await?;
 
@@ -177,6 +198,7 @@ pub struct TransactionBlocks {
 pub async fn verify_transactions(
     user: &mut GooseUser,
     blocks: Arc<TransactionBlocks>,
+    sent_transactions: Arc<Mutex<Vec<Felt>>>,
 ) -> TransactionResult {
     let transactions = mem::take(
         &mut user
@@ -185,6 +207,11 @@ pub async fn verify_transactions(
             .prev_tx,
     );
 
+    sent_transactions
+        .lock()
+        .expect("Sent transactions lock is never poisoned")
+        .extend_from_slice(&transactions);
+
     for (index, tx) in transactions.iter().enumerate() {
         let (status, mut metrics) =
             send_request::<TransactionStatus>(user, JsonRpcMethod::GetTransactionStatus, tx)
@@ -237,12 +264,17 @@ pub async fn verify_transactions(
     Ok(())
 }
 
+/// Path requests are posted to, relative to the base url of the goose user which is the full
+/// RPC url, an absolute path such as `/` would drop its path like `/rpc/v0_7`
+pub const RPC_PATH: &str = "";
+
 const WAIT_FOR_TX_TIMEOUT: Duration = Duration::from_secs(600);
 
 /// This function is different then `crate::utils::wait_for_tx` due to it using the goose requester
 pub async fn wait_for_tx_with_goose(
     user: &mut GooseUser,
     tx_hash: Felt,
+    accept_reverted: bool,
 ) -> Result<(), Box<TransactionError>> {
     let start = SystemTime::now();
 
@@ -267,6 +299,9 @@ pub async fn wait_for_tx_with_goose(
                 ExecutionResult::Succeeded => {
                     return Ok(());
                 }
+                ExecutionResult::Reverted { .. } if accept_reverted => {
+                    return Ok(());
+                }
                 ExecutionResult::Reverted { reason } => {
                     return user.set_failure(
                         &(reverted_tag() + reason),
@@ -303,9 +338,62 @@ pub async fn send_execution<T: DeserializeOwned>(
     user: &mut GooseUser,
     calls: Vec<Call>,
     nonce: Felt,
-    from_account: &SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>,
+    from_account: &StarknetAccount,
     method: JsonRpcMethod,
 ) -> Result<(T, GooseRequestMetric), Box<TransactionError>> {
+    let param = invoke_request(calls, nonce, MAX_FEE, from_account)
+        .await
+        .map_err(|error| signing_failure(user, "Execution could not be signed", &error))?;
+
+    send_request(user, method, param).await
+}
+
+/// Fails the current goose transaction when a transaction could not be signed,
+/// as no request was sent that could carry the failure
+pub fn signing_failure(
+    user: &GooseUser,
+    tag: &str,
+    error: &impl std::fmt::Display,
+) -> Box<TransactionError> {
+    tracing::error!("{tag}: {error}");
+
+    let raw = GooseRawRequest {
+        method: GooseMethod::Post,
+        url: user.base_url.to_string(),
+        headers: Vec::new(),
+        body: String::new(),
+    };
+
+    Box::new(TransactionError::RequestFailed {
+        raw_request: GooseRequestMetric {
+            elapsed: user.started.elapsed().as_millis() as u64,
+            scenario_index: 0,
+            scenario_name: String::new(),
+            transaction_index: String::new(),
+            transaction_name: String::new(),
+            raw,
+            name: tag.to_owned(),
+            final_url: String::new(),
+            redirected: false,
+            response_time: 0,
+            status_code: 0,
+            success: false,
+            update: false,
+            user: user.weighted_users_index,
+            error: error.to_string(),
+            coordinated_omission_elapsed: 0,
+            user_cadence: 0,
+        },
+    })
+}
+
+/// Builds a signed invoke transaction of `calls` from `from_account`
+pub async fn invoke_request(
+    calls: Vec<Call>,
+    nonce: Felt,
+    max_fee: Felt,
+    from_account: &StarknetAccount,
+) -> Result<BroadcastedInvokeTransaction, <StarknetAccount as Account>::SignError> {
     let calldata = from_account.encode_calls(&calls);
 
     #[allow(dead_code)] // Removes warning for unused fields, we need them to properly transmute
@@ -318,26 +406,23 @@ pub async fn send_execution<T: DeserializeOwned>(
     let raw_exec = FakeRawExecution {
         calls,
         nonce,
-        max_fee: MAX_FEE,
+        max_fee,
     };
 
     // TODO: We cannot right now construct RawExecution directly and need to use this hack
     // see https://github.com/xJonathanLEI/starknet-rs/issues/538
     let raw_exec = unsafe { mem::transmute::<FakeRawExecution, RawExecutionV1>(raw_exec) };
 
-    let param = BroadcastedInvokeTransaction::V1(BroadcastedInvokeTransactionV1 {
-        sender_address: from_account.address(),
-        calldata,
-        max_fee: MAX_FEE,
-        signature: from_account
-            .sign_execution_v1(&raw_exec, false)
-            .await
-            .expect("Raw Execution should be correctly constructed for signature"),
-        nonce,
-        is_query: false,
-    });
-
-    send_request(user, method, param).await
+    Ok(BroadcastedInvokeTransaction::V1(
+        BroadcastedInvokeTransactionV1 {
+            sender_address: from_account.address(),
+            calldata,
+            max_fee,
+            signature: from_account.sign_execution_v1(&raw_exec, false).await?,
+            nonce,
+            is_query: false,
+        },
+    ))
 }
 
 /// Sends request via goose, returning the successful json rpc response
@@ -346,7 +431,16 @@ pub async fn send_request<T: DeserializeOwned>(
     method: JsonRpcMethod,
     param: impl Serialize,
 ) -> Result<(T, GooseRequestMetric), Box<TransactionError>> {
-    let (body, mut metrics) = raw_send_request(user, method, param).await?;
+    send_request_params(user, method, [param]).await
+}
+
+/// Sends request with the whole `params` via goose, returning the successful json rpc response
+pub async fn send_request_params<T: DeserializeOwned>(
+    user: &mut GooseUser,
+    method: JsonRpcMethod,
+    params: impl Serialize,
+) -> Result<(T, GooseRequestMetric), Box<TransactionError>> {
+    let (body, mut metrics) = raw_send_request_params(user, method, params).await?;
 
     match body {
         JsonRpcResponse::Success { result, .. } => Ok((result, metrics)),
@@ -364,36 +458,267 @@ pub async fn send_request<T: DeserializeOwned>(
     }
 }
 
+// Copied from https://docs.rs/starknet-providers/0.12.0/src/starknet_providers/jsonrpc/transports/http.rs.html#21-27
+#[derive(Debug, Serialize)]
+struct JsonRpcRequest<T> {
+    id: u64,
+    jsonrpc: &'static str,
+    method: JsonRpcMethod,
+    params: T,
+}
+
 /// Sends request via goose, returning the deserialized response
 pub async fn raw_send_request<T: DeserializeOwned>(
     user: &mut GooseUser,
     method: JsonRpcMethod,
     param: impl Serialize,
 ) -> Result<(JsonRpcResponse<T>, GooseRequestMetric), Box<TransactionError>> {
-    // Copied from https://docs.rs/starknet-providers/0.12.0/src/starknet_providers/jsonrpc/transports/http.rs.html#21-27
-    #[derive(Debug, Serialize)]
-    struct JsonRpcRequest<T> {
-        id: u64,
-        jsonrpc: &'static str,
-        method: JsonRpcMethod,
-        params: T,
-    }
+    raw_send_request_params(user, method, [param]).await
+}
 
+/// Sends request with the whole `params` via goose, returning the deserialized response
+pub async fn raw_send_request_params<T: DeserializeOwned>(
+    user: &mut GooseUser,
+    method: JsonRpcMethod,
+    params: impl Serialize,
+) -> Result<(JsonRpcResponse<T>, GooseRequestMetric), Box<TransactionError>> {
     let request = JsonRpcRequest {
         id: 1,
         jsonrpc: "2.0",
         method,
-        params: [param],
+        params,
     };
 
-    let goose_response = user.post_json("/", &request).await?;
+    let goose_response = user.post_json(RPC_PATH, &request).await?;
+    let mut metric = goose_response.request;
+
+    let body = goose_response
+        .response
+        .map_err(TransactionError::Reqwest)?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(TransactionError::Reqwest)?;
+
+    spec::validate_response(method, &body);
+
+    match serde_json::from_value(body) {
+        Ok(body) => Ok((body, metric)),
+        Err(error) => Err(user
+            .set_failure(
+                "Response could not be decoded",
+                &mut metric,
+                None,
+                Some(&error.to_string()),
+            )
+            .unwrap_err()), // SAFETY: This always returns a error
+    }
+}
+
+/// Groups requests into JSON-RPC batches of `size` items, keeping track of how each item went
+#[derive(Debug)]
+pub struct Batch {
+    pub size: u64,
+    pub stats: Mutex<BatchStats>,
+}
+
+impl Batch {
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            stats: Mutex::default(),
+        }
+    }
+
+    pub fn record<T>(&self, time: u64, responses: &[JsonRpcResponse<T>]) {
+        let mut stats = self.stats();
+
+        stats.record_batch(time);
+
+        for response in responses {
+            match response {
+                JsonRpcResponse::Success { .. } => stats.record_item(None),
+                JsonRpcResponse::Error { error, .. } => {
+                    stats.record_item(Some((error.code, &error.message)))
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> MutexGuard<'_, BatchStats> {
+        self.stats
+            .lock()
+            .expect("Batch stats lock is never poisoned")
+    }
+}
+
+/// Sends a request for each of the `params` as a single JSON-RPC batch via goose,
+/// returning the responses in the same order as the params
+pub async fn raw_send_batch<T: DeserializeOwned>(
+    user: &mut GooseUser,
+    method: JsonRpcMethod,
+    params: Vec<impl Serialize>,
+) -> Result<(Vec<JsonRpcResponse<T>>, GooseRequestMetric), Box<TransactionError>> {
+    let requests: Vec<_> = params
+        .into_iter()
+        .enumerate()
+        .map(|(id, params)| JsonRpcRequest {
+            id: id as u64,
+            jsonrpc: "2.0",
+            method,
+            params,
+        })
+        .collect();
+    let amount = requests.len();
+
+    let goose_response = user.post_json(RPC_PATH, &requests).await?;
+    let mut metric = goose_response.request;
 
     let body = goose_response
         .response
         .map_err(TransactionError::Reqwest)?
-        .json::<JsonRpcResponse<T>>()
+        .json::<serde_json::Value>()
         .await
         .map_err(TransactionError::Reqwest)?;
 
-    Ok((body, goose_response.request))
+    if let serde_json::Value::Array(responses) = &body {
+        for response in responses {
+            spec::validate_response(method, response);
+        }
+    }
+
+    // Nodes without batch support usually answer with a single error object
+    let responses = match serde_json::from_value::<Vec<JsonRpcResponse<T>>>(body.clone()) {
+        Ok(responses) => responses,
+        Err(_) => {
+            return Err(user
+                .set_failure(
+                    "Batch response is not an array of responses",
+                    &mut metric,
+                    None,
+                    Some(&body.to_string()),
+                )
+                .unwrap_err()); // SAFETY: This always returns a error
+        }
+    };
+
+    let received = responses.len();
+
+    let Some(responses) = order_batch_responses(responses, amount) else {
+        let tag =
+            format!("Batch of {amount} requests got {received} responses not matching their ids");
+
+        return Err(user.set_failure(&tag, &mut metric, None, None).unwrap_err());
+        // SAFETY: This always returns a error
+    };
+
+    Ok((responses, metric))
+}
+
+/// Put the responses to a batch of `amount` requests, which may be answered in any order,
+/// in the order of the requests, as long as there is exactly one response for each of them
+fn order_batch_responses<T>(
+    mut responses: Vec<JsonRpcResponse<T>>,
+    amount: usize,
+) -> Option<Vec<JsonRpcResponse<T>>> {
+    responses.sort_by_key(|response| match response {
+        JsonRpcResponse::Success { id, .. } | JsonRpcResponse::Error { id, .. } => *id,
+    });
+
+    let ids_match = responses.iter().enumerate().all(|(index, response)| {
+        matches!(response, JsonRpcResponse::Success { id, .. } | JsonRpcResponse::Error { id, .. } if *id == index as u64)
+    });
+
+    (responses.len() == amount && ids_match).then_some(responses)
+}
+
+/// Read parameters that are a list are the whole positional parameters,
+/// anything else is the only parameter
+pub fn positional_params(params: serde_json::Value) -> serde_json::Value {
+    match params {
+        serde_json::Value::Array(_) => params,
+        param => serde_json::Value::Array(vec![param]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use goose::config::GooseConfiguration;
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn requests_keep_the_url_path() {
+        for url in [
+            "http://localhost:9944/rpc/v0_7",
+            "http://localhost:9944/rpc/v0_7/",
+        ] {
+            let user = GooseUser::single(Url::parse(url).unwrap(), &GooseConfiguration::default())
+                .unwrap();
+
+            assert_eq!(user.build_url(RPC_PATH).unwrap(), url);
+        }
+
+        let user = GooseUser::single(
+            Url::parse("http://localhost:9944").unwrap(),
+            &GooseConfiguration::default(),
+        )
+        .unwrap();
+
+        assert_eq!(user.build_url(RPC_PATH).unwrap(), "http://localhost:9944/");
+    }
+
+    /// Responses to a batch as the node sends them
+    fn batch_responses(body: serde_json::Value) -> Vec<JsonRpcResponse<Felt>> {
+        serde_json::from_value(body).unwrap()
+    }
+
+    /// Ids of the responses, along with their result or error code
+    fn ids(responses: &[JsonRpcResponse<Felt>]) -> Vec<(u64, Result<Felt, i64>)> {
+        responses
+            .iter()
+            .map(|response| match response {
+                JsonRpcResponse::Success { id, result } => (*id, Ok(*result)),
+                JsonRpcResponse::Error { id, error } => (*id, Err(error.code)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn out_of_order_batch_responses() {
+        let responses = batch_responses(serde_json::json!([
+            { "jsonrpc": "2.0", "id": 2, "result": "0x2" },
+            { "jsonrpc": "2.0", "id": 0, "result": "0x0" },
+            { "jsonrpc": "2.0", "id": 1, "error": { "code": 52, "message": "Invalid transaction nonce" } },
+        ]));
+
+        let ordered = order_batch_responses(responses, 3).unwrap();
+
+        assert_eq!(
+            ids(&ordered),
+            [(0, Ok(Felt::ZERO)), (1, Err(52)), (2, Ok(Felt::TWO))]
+        );
+
+        // Errors are attributed to the item they answer
+        let batch = Batch::new(3);
+        batch.record(10, &ordered);
+
+        let stats = batch.stats();
+        assert_eq!((stats.items, stats.failed_items), (3, 1));
+        assert_eq!(stats.errors[&52].1, 1);
+    }
+
+    #[test]
+    fn mismatching_batch_responses() {
+        let response = |id: u64| serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": "0x1" });
+
+        // Missing, duplicated and unknown ids
+        for body in [
+            serde_json::json!([response(1), response(0)]),
+            serde_json::json!([response(0), response(1), response(1)]),
+            serde_json::json!([response(0), response(2), response(3)]),
+        ] {
+            assert!(order_batch_responses(batch_responses(body), 3).is_none());
+        }
+    }
 }
@@ -1,105 +1,482 @@
-use std::{fs::File, mem, sync::Arc};
+use std::{fs::File, mem, sync::Arc, time::Instant};
 
-use color_eyre::eyre::bail;
+use color_eyre::eyre::{bail, ensure};
+use futures::future::LocalBoxFuture;
+use starknet::accounts::Account;
 use tracing::info;
 
 use crate::{
-    config::GatlingConfig,
-    metrics::{BenchmarkReport, GlobalReport},
+    artifacts::Artifacts,
+    config::{GatlingConfig, Shooters, Transport},
+    generators::parameters::ReadParameters,
+    metrics::{BenchmarkReport, GlobalReport, MetricResult},
+    spec,
 };
 
 use self::{
+    budget::Budget,
+    endpoints::Endpoints,
+    goose::Batch,
     setup::GatlingSetup,
-    shooters::{mint::MintShooter, transfer::TransferShooter, Shooter, ShooterAttack},
+    shooters::{
+        declare::DeclareShooter,
+        deploy_account::DeployAccountShooter,
+        events::EventsShooter,
+        invalid::InvalidShooter,
+        mint::MintShooter,
+        revert::RevertShooter,
+        transfer::TransferShooter,
+        udc::UdcShooter,
+        workload::{Compute, Pedersen, Poseidon, Storage, WorkloadShooter},
+        Shooter, ShooterAttack, SubmissionHooks,
+    },
 };
 
+mod budget;
+mod check;
+mod diff;
+mod endpoints;
 mod goose;
+mod propagation;
 mod setup;
 mod shooters;
+mod signer;
+mod subscriptions;
+mod teardown;
+mod ws;
 
 pub async fn shoot(mut config: GatlingConfig) -> color_eyre::Result<()> {
     let shooters = mem::take(&mut config.run.shooters);
-    let total_txs: u64 = shooters.iter().map(|s| s.shoot).sum();
 
     let mut shooter_setup = GatlingSetup::from_config(config).await?;
+    shooter_setup.check_public_chain()?;
+    shooter_setup.load_deployer().await?;
+    shooter_setup.check_deployer(&shooters).await?;
     shooter_setup.setup_accounts().await?;
 
-    let mut global_report = GlobalReport {
-        users: shooter_setup.config().run.concurrency,
+    let mut global_report = global_report(&shooter_setup);
+
+    let writes = run_shooters(&mut shooter_setup, shooters).await?;
+    writes.finish(&shooter_setup, &mut global_report).await?;
+
+    write_report(shooter_setup.config(), &mut global_report)
+}
+
+pub async fn read(config: GatlingConfig) -> color_eyre::Result<()> {
+    let shooter_setup = GatlingSetup::from_config(config).await?;
+
+    let mut global_report = global_report(&shooter_setup);
+
+    let parameters = read_parameters(&shooter_setup).await?;
+
+    global_report.benches = run_read_benches(shooter_setup.config(), &parameters, None).await?;
+
+    write_report(shooter_setup.config(), &mut global_report)
+}
+
+/// Run the read benchmarks once at idle as a baseline, then again
+/// concurrently with the write shooters, each with their own goose users
+pub async fn shoot_and_read(mut config: GatlingConfig) -> color_eyre::Result<()> {
+    let shooters = mem::take(&mut config.run.shooters);
+
+    let mut shooter_setup = GatlingSetup::from_config(config).await?;
+    shooter_setup.check_public_chain()?;
+    shooter_setup.load_deployer().await?;
+    shooter_setup.check_deployer(&shooters).await?;
+    shooter_setup.setup_accounts().await?;
+
+    let mut global_report = global_report(&shooter_setup);
+
+    let read_config = shooter_setup.config().clone();
+    let parameters = read_parameters(&shooter_setup).await?;
+
+    info!("Measuring read latency at idle");
+    let idle_reads = run_read_benches(&read_config, &parameters, Some("Idle")).await?;
+
+    info!("Measuring read latency under write load");
+    let ((writes, writes_end), (loaded_reads, reads_end)) = tokio::join!(
+        async {
+            let writes = run_shooters(&mut shooter_setup, shooters).await;
+            (writes, Instant::now())
+        },
+        async {
+            let condition = Some("Under Write Load");
+            let reads = run_read_benches(&read_config, &parameters, condition).await;
+            (reads, Instant::now())
+        }
+    );
+
+    let (writes, loaded_reads) = (writes?, loaded_reads?);
+
+    if reads_end > writes_end {
+        tracing::warn!(
+            "Reads outlasted the write shooters by {:?}, part of them were measured at idle",
+            reads_end - writes_end
+        );
+    }
+
+    global_report.benches.extend(idle_reads);
+    global_report.benches.extend(loaded_reads);
+    writes.finish(&shooter_setup, &mut global_report).await?;
+
+    write_report(shooter_setup.config(), &mut global_report)
+}
+
+/// Run the read benchmarks against both the node under test and the reference node,
+/// reporting where their responses differ
+pub async fn diff_read(config: GatlingConfig) -> color_eyre::Result<()> {
+    let shooter_setup = GatlingSetup::from_config(config).await?;
+
+    let mut global_report = global_report(&shooter_setup);
+
+    let parameters = read_parameters(&shooter_setup).await?;
+    let config = shooter_setup.config();
+
+    for (read_bench, parameters) in config.run.read_benches.iter().zip(parameters) {
+        let report = diff::diff_read_bench(config, read_bench, parameters).await?;
+        global_report.benches.push(report);
+    }
+
+    write_report(config, &mut global_report)
+}
+
+pub async fn subscribe(config: GatlingConfig) -> color_eyre::Result<()> {
+    let shooter_setup = GatlingSetup::from_config(config).await?;
+
+    let mut global_report = global_report(&shooter_setup);
+
+    if let Some(url) = &shooter_setup.config().rpc.ws_url {
+        subscriptions::check_spec_version(url).await?;
+    }
+
+    for bench in &shooter_setup.config().run.subscriptions {
+        let report = subscriptions::subscription_bench(&shooter_setup, bench).await?;
+        global_report.benches.push(report);
+    }
+
+    write_report(shooter_setup.config(), &mut global_report)
+}
+
+/// Send the fee tokens left on the accounts of the goose users back to the deployer
+pub async fn teardown(config: GatlingConfig) -> color_eyre::Result<()> {
+    let mut shooter_setup = GatlingSetup::from_config(config).await?;
+    shooter_setup.check_public_chain()?;
+    shooter_setup.load_deployer().await?;
+
+    let mut global_report = global_report(&shooter_setup);
+
+    let report = teardown::sweep_accounts(&shooter_setup).await?;
+    global_report.benches.push(report);
+
+    write_report(shooter_setup.config(), &mut global_report)
+}
+
+/// Validate the configuration and log what the benchmarks will do, without sending anything
+pub fn check(config: GatlingConfig) -> color_eyre::Result<()> {
+    check::check_config(&config)
+}
+
+/// Print the shooters gatling knows of and the benchmarks of the configuration
+pub fn list(config: &GatlingConfig) {
+    println!("Available shooters:");
+    for kind in &SHOOTERS {
+        println!(
+            "  {}{}",
+            kind.name,
+            if kind.batchable {
+                ""
+            } else {
+                " (no batch submission)"
+            }
+        );
+    }
+
+    println!("Shooters:");
+    for shooter in &config.run.shooters {
+        println!("  {}: {} transactions", shooter.name, shooter.shoot);
+    }
+
+    println!("Read benches:");
+    for bench in &config.run.read_benches {
+        println!(
+            "  {}: {} {} requests",
+            bench.name,
+            bench.num_requests,
+            spec::method_name(bench.method)
+        );
+    }
+
+    println!("Subscriptions:");
+    for bench in &config.run.subscriptions {
+        println!("  {}: {} subscribers", bench.name, bench.subscribers);
+    }
+}
+
+/// Report of a command, filled with the reports of its benchmarks
+fn global_report(setup: &GatlingSetup) -> GlobalReport {
+    GlobalReport {
+        users: setup.config().run.concurrency,
+        spec_version: setup.spec_version().to_owned(),
         all_bench_report: None,
         benches: Vec::new(),
         extra: crate::utils::sysinfo_string(),
+    }
+}
+
+/// What the write shooters of a run leave behind
+struct WriteResults {
+    benches: Vec<BenchmarkReport>,
+    total_txs: u64,
+    blocks: Option<(u64, u64)>,
+    artifacts: Artifacts,
+}
+
+impl WriteResults {
+    /// Add the reports of the shooters and of the whole run to the global report,
+    /// and save the artifacts if configured
+    async fn finish(
+        self,
+        setup: &GatlingSetup,
+        global_report: &mut GlobalReport,
+    ) -> color_eyre::Result<()> {
+        global_report.benches.extend(self.benches);
+
+        let mut all_bench_report = BenchmarkReport::new("".into(), self.total_txs as usize);
+
+        if let Some((start_block, end_block)) = self.blocks {
+            info!("Start and End Blocks: {start_block}, {end_block}");
+
+            let rpc_result = all_bench_report
+                .with_block_range(setup.verification_client(), start_block, end_block)
+                .await;
+
+            global_report.all_bench_report = Some(all_bench_report);
+
+            if let Err(error) = rpc_result {
+                tracing::error!("Failed to get block range: {error}")
+            }
+        }
+
+        if let Some(artifacts_path) = &setup.config().report.artifacts_location {
+            self.artifacts.save(artifacts_path)?;
+            info!("Saved artifacts to {}", artifacts_path.display());
+        }
+
+        Ok(())
+    }
+}
+
+async fn run_shooters(
+    shooter_setup: &mut GatlingSetup,
+    shooters: Vec<Shooters>,
+) -> color_eyre::Result<WriteResults> {
+    let mut results = WriteResults {
+        benches: Vec::new(),
+        total_txs: shooters.iter().map(|s| s.shoot).sum(),
+        blocks: None,
+        artifacts: Artifacts {
+            accounts: shooter_setup
+                .accounts()
+                .iter()
+                .map(|account| account.address())
+                .collect(),
+            ..Default::default()
+        },
     };
 
-    let mut blocks = Option::<(u64, u64)>::None;
+    let budget = match shooter_setup.config().run.max_total_fee {
+        Some(max_total_fee) => Some(Arc::new(
+            Budget::new(shooter_setup.verification_client().clone(), max_total_fee).await?,
+        )),
+        None => None,
+    };
 
     for shooter in shooters {
+        if budget.as_ref().is_some_and(|budget| budget.exceeded()) {
+            tracing::warn!("Fee budget exceeded, not running {}", shooter.name);
+            continue;
+        }
+
         if shooter.shoot == 0 {
             tracing::info!("Skipping {} transfers", shooter.name);
             continue;
         }
 
-        let (report, first_block, last_block) = match shooter.name.as_str() {
-            "transfer" => {
-                make_report_over_shooter::<TransferShooter>(&mut shooter_setup, shooter.shoot)
-                    .await?
-            }
-            "mint" => {
-                make_report_over_shooter::<MintShooter>(&mut shooter_setup, shooter.shoot).await?
-            }
-            name => bail!("Shooter `{name}` not found!"),
+        let artifacts = &mut results.artifacts;
+
+        let Some(kind) = ShooterKind::find(&shooter.name) else {
+            bail!("Shooter `{}` not found!", shooter.name);
         };
 
-        global_report.benches.push(report);
-        blocks.get_or_insert((first_block, last_block)).1 = last_block;
-    }
+        let (report, first_block, last_block) =
+            (kind.run)(shooter_setup, &shooter, artifacts, budget.as_ref()).await?;
 
-    let mut all_bench_report = BenchmarkReport::new("".into(), total_txs as usize);
+        results.benches.push(report);
+        results.blocks.get_or_insert((first_block, last_block)).1 = last_block;
+    }
 
-    if let Some((start_block, end_block)) = blocks {
-        info!("Start and End Blocks: {start_block}, {end_block}");
+    if let Some(budget) = budget {
+        let mut report = BenchmarkReport::new("Fee Budget".into(), 0);
+
+        report.metrics.extend_from_slice(&[
+            MetricResult {
+                name: "Total Fees".into(),
+                unit: "fee tokens",
+                value: budget.spent().to_string().into(),
+            },
+            MetricResult {
+                name: "Budget Exceeded".into(),
+                unit: "",
+                value: budget.exceeded().into(),
+            },
+        ]);
+
+        results.benches.push(report);
+    }
 
-        let rpc_result = all_bench_report
-            .with_block_range(shooter_setup.rpc_client(), start_block, end_block)
-            .await;
+    Ok(results)
+}
 
-        global_report.all_bench_report = Some(all_bench_report);
+/// Runs the shooter on the setup, returning its report and the blocks its transactions landed in
+type ShooterRun = for<'a> fn(
+    &'a mut GatlingSetup,
+    &'a Shooters,
+    &'a mut Artifacts,
+    Option<&'a Arc<Budget>>,
+)
+    -> LocalBoxFuture<'a, color_eyre::Result<(BenchmarkReport, u64, u64)>>;
+
+/// A shooter that can be used in `run.shooters`, under the name configurations refer to it by
+struct ShooterKind {
+    name: &'static str,
+    /// Whether the shooter supports batch submission
+    batchable: bool,
+    validate: fn(&GatlingConfig, &Shooters) -> color_eyre::Result<()>,
+    run: ShooterRun,
+}
 
-        if let Err(error) = rpc_result {
-            tracing::error!("Failed to get block range: {error}")
+impl ShooterKind {
+    const fn of<S: Shooter + Send + Sync + 'static>(name: &'static str) -> Self {
+        Self {
+            name,
+            batchable: S::BATCHABLE,
+            validate: S::validate,
+            run: run_shooter::<S>,
         }
     }
 
-    let report_path = shooter_setup
-        .config()
-        .report
-        .output_location
-        .with_extension("json");
-
-    serde_json::to_writer_pretty(File::create(report_path)?, &global_report)?;
+    fn find(name: &str) -> Option<&'static Self> {
+        SHOOTERS.iter().find(|kind| kind.name == name)
+    }
+}
 
-    Ok(())
+static SHOOTERS: [ShooterKind; 12] = [
+    ShooterKind::of::<TransferShooter>("transfer"),
+    ShooterKind::of::<MintShooter>("mint"),
+    ShooterKind::of::<DeclareShooter>("declare"),
+    ShooterKind::of::<DeployAccountShooter>("deploy_account"),
+    ShooterKind::of::<UdcShooter>("udc"),
+    ShooterKind::of::<InvalidShooter>("invalid"),
+    ShooterKind::of::<RevertShooter>("revert"),
+    ShooterKind::of::<WorkloadShooter<Compute>>("compute"),
+    ShooterKind::of::<WorkloadShooter<Storage>>("storage"),
+    ShooterKind::of::<EventsShooter>("events"),
+    ShooterKind::of::<WorkloadShooter<Poseidon>>("poseidon"),
+    ShooterKind::of::<WorkloadShooter<Pedersen>>("pedersen"),
+];
+
+fn run_shooter<'a, S: Shooter + Send + Sync + 'static>(
+    setup: &'a mut GatlingSetup,
+    shooter_config: &'a Shooters,
+    artifacts: &'a mut Artifacts,
+    budget: Option<&'a Arc<Budget>>,
+) -> LocalBoxFuture<'a, color_eyre::Result<(BenchmarkReport, u64, u64)>> {
+    Box::pin(make_report_over_shooter::<S>(
+        setup,
+        shooter_config,
+        artifacts,
+        budget,
+    ))
 }
 
 async fn make_report_over_shooter<S: Shooter + Send + Sync + 'static>(
     setup: &mut GatlingSetup,
-    amount: u64,
+    shooter_config: &Shooters,
+    artifacts: &mut Artifacts,
+    budget: Option<&Arc<Budget>>,
 ) -> color_eyre::Result<(BenchmarkReport, u64, u64)> {
-    let shooter = S::setup(setup).await?;
-    let goose_config = S::get_goose_config(setup.config(), amount)?;
+    shooter_config.calls_per_tx.validate()?;
+    S::validate(setup.config(), shooter_config)?;
+
+    if let Some(batch_size) = shooter_config.batch_size {
+        ensure!(
+            batch_size != 0,
+            "Shooter `{}` has a batch size of 0",
+            shooter_config.name
+        );
+        ensure!(
+            S::BATCHABLE,
+            "Shooter `{}` does not support batch submission",
+            shooter_config.name
+        );
+    }
+
+    let batch_size = shooter_config.batch_size.unwrap_or(1);
+    let batch = shooter_config
+        .batch_size
+        .map(|size| Arc::new(Batch::new(size)));
+
+    // Each goose iteration submits a whole batch
+    let goose_config = S::get_goose_config(setup.config(), shooter_config.shoot / batch_size)?;
+    let shooter = Arc::new(S::setup(setup, shooter_config).await?);
+
+    let (tracker, observers) = match &setup.config().report.propagation {
+        Some(propagation) => {
+            let (tracker, observers) = propagation::observe(propagation)?;
+            (Some(tracker), Some(observers))
+        }
+        None => (None, None),
+    };
+
+    let attack = shooter
+        .clone()
+        .goose_attack(
+            goose_config,
+            setup.accounts().to_vec(),
+            shooter_config.calls_per_tx,
+            batch.clone(),
+            Arc::new(Endpoints::from_config(&setup.config().rpc)?),
+            SubmissionHooks {
+                tracker,
+                budget: budget.cloned(),
+            },
+        )
+        .await?;
+
+    // Fees of the last transactions are charged before the next shooter starts
+    let skipped = match budget {
+        Some(budget) => {
+            budget.settle().await;
+            budget.take_skipped()
+        }
+        None => 0,
+    };
 
     let ShooterAttack {
-        goose_metrics,
+        ref goose_metrics,
         first_block,
         last_block,
-    } = Arc::new(shooter)
-        .goose_attack(goose_config, setup.accounts().to_vec())
-        .await?;
+        ..
+    } = attack;
 
-    let mut report = BenchmarkReport::new(S::NAME.to_string(), goose_metrics.scenarios[0].counter);
+    // Runs skipped once over budget did not send anything
+    let mut report = BenchmarkReport::new(
+        S::NAME.to_string(),
+        (goose_metrics.scenarios[0].counter - skipped) * batch_size as usize,
+    );
 
     let rpc_result = report
-        .with_block_range(setup.rpc_client(), first_block + 1, last_block)
+        .with_block_range(setup.verification_client(), first_block + 1, last_block)
         .await;
 
     let num_blocks = setup.config().report.num_blocks;
@@ -108,48 +485,135 @@ async fn make_report_over_shooter<S: Shooter + Send + Sync + 'static>(
         tracing::error!("Failed to get block range: {error}")
     } else if num_blocks != 0 {
         report
-            .with_last_x_blocks(setup.rpc_client(), num_blocks)
+            .with_last_x_blocks(setup.verification_client(), num_blocks)
             .await?;
     }
 
-    report.with_goose_write_metrics(&goose_metrics)?;
+    report.with_goose_write_metrics(goose_metrics)?;
+
+    if let Some(observers) = observers {
+        observers.finish(&mut report).await?;
+    }
+
+    if let Some(batch) = batch {
+        report.with_batch_metrics(&batch.stats(), Some("Submission"));
+    }
+
+    if let Err(error) = shooter.report(setup, &attack, &mut report).await {
+        tracing::error!("Failed to compute {} metrics: {error}", S::NAME)
+    }
+
+    shooter.artifacts(artifacts);
+    artifacts
+        .transactions
+        .extend_from_slice(&attack.transactions);
+
     Ok((report, first_block, last_block))
 }
 
-pub async fn read(config: GatlingConfig) -> color_eyre::Result<()> {
-    let shooter_setup = GatlingSetup::from_config(config).await?;
-
-    let mut global_report = GlobalReport {
-        users: shooter_setup.config().run.concurrency,
-        all_bench_report: None,
-        benches: Vec::new(),
-        extra: crate::utils::sysinfo_string(),
+/// Build the parameters of every read benchmark, sampling data before any request is sent
+async fn read_parameters(setup: &GatlingSetup) -> color_eyre::Result<Vec<ReadParameters>> {
+    let artifacts = match &setup.config().report.artifacts_location {
+        Some(path) if path.exists() => Some(Artifacts::load(path)?),
+        Some(path) => {
+            tracing::warn!("No artifacts found at {}", path.display());
+            None
+        }
+        None => None,
     };
 
-    for read_bench in &shooter_setup.config().run.read_benches {
+    let mut parameters = Vec::new();
+
+    for read_bench in &setup.config().run.read_benches {
+        parameters.push(
+            ReadParameters::from_config(setup.rpc_client(), read_bench, artifacts.as_ref()).await?,
+        );
+    }
+
+    Ok(parameters)
+}
+
+/// Run all the read benchmarks one after the other, `condition` is appended
+/// to their name to tell apart runs of the same benchmark
+async fn run_read_benches(
+    config: &GatlingConfig,
+    parameters: &[ReadParameters],
+    condition: Option<&str>,
+) -> color_eyre::Result<Vec<BenchmarkReport>> {
+    let mut reports = Vec::new();
+
+    for (read_bench, parameters) in config.run.read_benches.iter().zip(parameters) {
+        ensure!(
+            read_bench.batch_size != Some(0),
+            "Read bench `{}` has a batch size of 0",
+            read_bench.name
+        );
+
+        let name = match condition {
+            Some(condition) => format!("{} ({condition})", read_bench.name),
+            None => read_bench.name.clone(),
+        };
+
+        if read_bench.transport == Transport::Websocket {
+            ensure!(
+                read_bench.batch_size.is_none(),
+                "Read bench `{}` cannot batch requests over WebSocket",
+                read_bench.name
+            );
+
+            let mut latencies = ws::read_method(
+                config,
+                read_bench.num_requests,
+                read_bench.method,
+                parameters.clone(),
+            )
+            .await?;
+
+            let amount = latencies.times.len() + latencies.failed as usize;
+            let mut report = BenchmarkReport::new(name, amount);
+
+            report.with_latencies(&mut latencies.times, latencies.failed, None);
+
+            reports.push(report);
+            continue;
+        }
+
+        let batch = read_bench.batch_size.map(|size| Arc::new(Batch::new(size)));
+
         let metrics = goose::read_method(
-            &shooter_setup,
+            config,
             read_bench.num_requests,
             read_bench.method,
-            read_bench.parameters_location.clone(),
+            parameters.clone(),
+            batch.clone(),
         )
         .await?;
 
-        let mut report =
-            BenchmarkReport::new(read_bench.name.clone(), metrics.scenarios[0].counter);
+        let batch_size = read_bench.batch_size.unwrap_or(1) as usize;
+        let mut report = BenchmarkReport::new(name, metrics.scenarios[0].counter * batch_size);
 
         report.with_goose_read_metrics(&metrics)?;
 
-        global_report.benches.push(report);
+        if let Some(batch) = batch {
+            report.with_batch_metrics(&batch.stats(), None);
+        }
+
+        reports.push(report);
     }
 
-    let report_path = shooter_setup
-        .config()
-        .report
-        .output_location
-        .with_extension("json");
+    Ok(reports)
+}
+
+/// Write the report, along with the spec violations found during the run if validating responses
+fn write_report(
+    config: &GatlingConfig,
+    global_report: &mut GlobalReport,
+) -> color_eyre::Result<()> {
+    global_report.benches.extend(spec::report());
+
+    let report_path = config.report.output_location.with_extension("json");
 
-    serde_json::to_writer_pretty(File::create(report_path)?, &global_report)?;
+    serde_json::to_writer_pretty(File::create(report_path)?, global_report)?;
 
     Ok(())
 }
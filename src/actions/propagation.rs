@@ -0,0 +1,222 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::eyre;
+use goose::goose::TransactionFunction;
+use starknet::{
+    core::types::Felt,
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::{JoinHandle, JoinSet},
+};
+use url::Url;
+
+use crate::{
+    config::PropagationConfig,
+    metrics::{BenchmarkReport, MetricResult},
+};
+
+use super::goose::submitted_transactions;
+
+/// How long an observer keeps polling a transaction after its submission
+const OBSERVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+type Submitted = (Felt, Instant);
+
+/// Forwards the transactions submitted by the goose users to the observers
+pub struct Tracker {
+    senders: Vec<UnboundedSender<Submitted>>,
+}
+
+impl Tracker {
+    /// Reports the transactions submitted by each run of `transaction` to the observers
+    pub fn track(self: &Arc<Self>, transaction: TransactionFunction) -> TransactionFunction {
+        let tracker = self.clone();
+
+        Arc::new(move |user| {
+            let (tracker, transaction) = (tracker.clone(), transaction.clone());
+
+            Box::pin(async move {
+                let sent = submitted_transactions(user).len();
+
+                let result = transaction(user).await;
+
+                let now = Instant::now();
+                for &hash in &submitted_transactions(user)[sent..] {
+                    for sender in &tracker.senders {
+                        // The observer only stops once the attack is over
+                        let _ = sender.send((hash, now));
+                    }
+                }
+
+                result
+            })
+        })
+    }
+}
+
+/// When an observer first reported a transaction, in milliseconds since its submission
+#[derive(Debug, Default, Clone, Copy)]
+struct Observed {
+    /// In the mempool or in the pending block
+    received: Option<u64>,
+    /// Executed, with a receipt
+    receipt: Option<u64>,
+    /// In an accepted block
+    accepted: Option<u64>,
+}
+
+/// Nodes polling the submitted transactions in the background
+pub struct Observers {
+    handles: Vec<(String, JoinHandle<Vec<Observed>>)>,
+}
+
+/// Start polling the transactions submitted through the returned tracker on every observer
+pub fn observe(config: &PropagationConfig) -> color_eyre::Result<(Arc<Tracker>, Observers)> {
+    let interval = Duration::from_millis(config.interval);
+
+    let mut senders = Vec::with_capacity(config.observers.len());
+    let mut handles = Vec::with_capacity(config.observers.len());
+
+    for url in &config.observers {
+        let client = JsonRpcClient::new(HttpTransport::new(Url::parse(url)?));
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        senders.push(sender);
+        handles.push((
+            url.clone(),
+            tokio::spawn(poll_observer(client, receiver, interval)),
+        ));
+    }
+
+    Ok((Arc::new(Tracker { senders }), Observers { handles }))
+}
+
+impl Observers {
+    /// Wait for the observers to see every transaction, or give up on them,
+    /// and add the propagation latencies of each observer to the report
+    pub async fn finish(self, report: &mut BenchmarkReport) -> color_eyre::Result<()> {
+        for (url, handle) in self.handles {
+            let observed = handle.await.map_err(|err| eyre!(err))?;
+
+            tracing::info!("Observer {url} polled {} transactions", observed.len());
+
+            let stages: [(_, Vec<_>); 3] = [
+                (
+                    "Received",
+                    observed.iter().filter_map(|o| o.received).collect(),
+                ),
+                (
+                    "Receipt",
+                    observed.iter().filter_map(|o| o.receipt).collect(),
+                ),
+                (
+                    "Accepted",
+                    observed.iter().filter_map(|o| o.accepted).collect(),
+                ),
+            ];
+
+            for (stage, mut times) in stages {
+                let metric = format!("Propagation to {url} {stage}");
+
+                report.metrics.push(MetricResult {
+                    name: format!("{metric} Missing").into(),
+                    unit: "transactions",
+                    value: (observed.len() - times.len()).into(),
+                });
+
+                report.with_latencies(&mut times, 0, Some(&metric));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Poll every transaction received on `submissions` on its own, until it is accepted or times out
+async fn poll_observer(
+    client: JsonRpcClient<HttpTransport>,
+    mut submissions: UnboundedReceiver<Submitted>,
+    interval: Duration,
+) -> Vec<Observed> {
+    let client = Arc::new(client);
+    let mut polling = JoinSet::new();
+
+    // The channel closes once the attack is over
+    while let Some((hash, at)) = submissions.recv().await {
+        polling.spawn(poll_transaction(client.clone(), hash, at, interval));
+    }
+
+    let mut done = Vec::new();
+
+    while let Some(result) = polling.join_next().await {
+        match result {
+            Ok(observed) => done.push(observed),
+            Err(error) => tracing::error!("Failed to poll a transaction on an observer: {error}"),
+        }
+    }
+
+    done
+}
+
+/// Poll the transaction `hash` submitted at `at` every `interval`, so that
+/// its latencies do not depend on how many other transactions are being polled
+async fn poll_transaction(
+    client: Arc<JsonRpcClient<HttpTransport>>,
+    hash: Felt,
+    at: Instant,
+    interval: Duration,
+) -> Observed {
+    let mut observed = Observed::default();
+
+    loop {
+        let next_poll = tokio::time::sleep(interval);
+
+        observed = poll(&client, hash, at, observed).await;
+
+        if observed.accepted.is_some() {
+            return observed;
+        }
+
+        if at.elapsed() >= OBSERVE_TIMEOUT {
+            tracing::warn!("Transaction {hash:#064x} was not accepted on an observer in time");
+            return observed;
+        }
+
+        next_poll.await;
+    }
+}
+
+/// Update what the observer knows about the transaction `hash` submitted at `at`
+async fn poll(
+    client: &JsonRpcClient<HttpTransport>,
+    hash: Felt,
+    at: Instant,
+    mut observed: Observed,
+) -> Observed {
+    let elapsed = || Some(at.elapsed().as_millis() as u64);
+
+    if observed.received.is_none() {
+        match client.get_transaction_status(hash).await {
+            Ok(_) => observed.received = elapsed(),
+            // Most likely not propagated yet
+            Err(_) => return observed,
+        }
+    }
+
+    if let Ok(receipt) = client.get_transaction_receipt(hash).await {
+        if observed.receipt.is_none() {
+            observed.receipt = elapsed();
+        }
+
+        if receipt.block.block_number().is_some() {
+            observed.accepted = elapsed();
+        }
+    }
+
+    observed
+}
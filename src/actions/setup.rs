@@ -1,14 +1,18 @@
-use crate::config::{ContractSourceConfig, GatlingConfig};
+use crate::config::{ContractSourceConfig, GatlingConfig, Shooters};
+use crate::spec;
+
+use super::shooters::deploy_account::FUNDING_CALLS_PER_TX;
+use super::signer::GatlingSigner;
 use crate::utils::wait_for_tx;
 use color_eyre::{
     eyre::{
-        Context, {bail, eyre},
+        Context, {bail, ensure, eyre},
     },
     Result,
 };
 
 use starknet::core::types::contract::SierraClass;
-use starknet::core::types::Call;
+use starknet::core::types::{Call, FunctionCall};
 use tokio::task::JoinSet;
 
 use std::path::Path;
@@ -17,13 +21,14 @@ use starknet::accounts::{
     Account, AccountFactory, ConnectedAccount, ExecutionEncoding, OpenZeppelinAccountFactory,
     SingleOwnerAccount,
 };
+use starknet::core::chain_id;
 use starknet::core::types::{
     contract::legacy::LegacyContractClass, BlockId, BlockTag, Felt, StarknetError,
 };
-use starknet::macros::{felt, selector};
+use starknet::core::utils::parse_cairo_short_string;
+use starknet::macros::{felt, selector, short_string};
 use starknet::providers::ProviderError;
 use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider};
-use starknet::signers::{LocalWallet, SigningKey};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -33,28 +38,106 @@ use url::Url;
 pub static MAX_FEE: Felt = felt!("0x6efb28c75a0000");
 pub static CHECK_INTERVAL: Duration = Duration::from_millis(500);
 
-pub type StarknetAccount = SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, LocalWallet>;
+/// Fee tokens given to each account of the goose users
+static ACCOUNT_FUNDING: Felt = felt!("0xFFFFFFFFFFFFFFF");
+
+/// RPC spec version the starknet-rs types are for, patch versions are compatible
+const SUPPORTED_SPEC_VERSION: &str = "0.7";
+
+/// Paths nodes serving several RPC versions commonly serve the supported one at
+const VERSIONED_PATHS: [&str; 3] = ["rpc/v0_7", "rpc/v0_7_1", "v0_7"];
+
+/// Chains where real funds are spent, only attacked when explicitly allowed
+const PUBLIC_CHAINS: [Felt; 3] = [
+    chain_id::MAINNET,
+    chain_id::SEPOLIA,
+    short_string!("SN_INTEGRATION_SEPOLIA"),
+];
+
+pub type StarknetAccount = SingleOwnerAccount<Arc<JsonRpcClient<HttpTransport>>, GatlingSigner>;
 
 pub struct GatlingSetup {
     config: GatlingConfig,
     starknet_rpc: Arc<JsonRpcClient<HttpTransport>>,
-    signer: LocalWallet,
-    deployer: StarknetAccount,
+    /// Client of the node transactions are verified on
+    verification_rpc: Arc<JsonRpcClient<HttpTransport>>,
+    /// Only loaded by [`GatlingSetup::load_deployer`] for the commands sending transactions
+    signer: Option<GatlingSigner>,
+    deployer: Option<StarknetAccount>,
     accounts: Vec<StarknetAccount>,
+    /// RPC spec version served by `rpc.url`
+    spec_version: String,
+    chain_id: Felt,
 }
 
 impl GatlingSetup {
-    pub async fn from_config(config: GatlingConfig) -> Result<Self> {
+    pub async fn from_config(mut config: GatlingConfig) -> Result<Self> {
+        let spec_version = supported_rpc_url(&mut config.rpc.url).await?;
+
+        let rpc = &mut config.rpc;
+        let other_urls = rpc
+            .endpoints
+            .iter_mut()
+            .map(|endpoint| &mut endpoint.url)
+            .chain(rpc.verification_url.as_mut())
+            .chain(rpc.reference_url.as_mut())
+            .chain(
+                config
+                    .report
+                    .propagation
+                    .iter_mut()
+                    .flat_map(|propagation| propagation.observers.iter_mut()),
+            );
+
+        for url in other_urls {
+            supported_rpc_url(url).await?;
+        }
+
         let starknet_rpc: Arc<JsonRpcClient<HttpTransport>> =
             Arc::new(starknet_rpc_provider(Url::parse(&config.clone().rpc.url)?));
+        let verification_rpc = match &config.rpc.verification_url {
+            Some(url) => Arc::new(starknet_rpc_provider(Url::parse(url)?)),
+            None => starknet_rpc.clone(),
+        };
+
+        if config.report.validate_responses {
+            spec::enable(&spec_version)?;
+        }
+
+        let chain_id = starknet_rpc.chain_id().await?;
 
-        let signer = LocalWallet::from(SigningKey::from_secret_scalar(config.deployer.signing_key));
+        match config.setup.chain_id {
+            Some(configured) if configured != chain_id => bail!(
+                "Configured chain id {} does not match the chain id {} of the node",
+                short_string(configured),
+                short_string(chain_id)
+            ),
+            Some(_) => {}
+            None => tracing::info!("Using chain id {} of the node", short_string(chain_id)),
+        }
+
+        Ok(Self {
+            config,
+            starknet_rpc,
+            verification_rpc,
+            signer: None,
+            deployer: None,
+            accounts: vec![],
+            spec_version,
+            chain_id,
+        })
+    }
+
+    /// Load the signer of the deployer, which may prompt for a keystore password
+    /// or start an external signer, so only commands sending transactions do it
+    pub async fn load_deployer(&mut self) -> Result<()> {
+        let signer = GatlingSigner::from_config(&self.config.deployer).await?;
         let mut deployer = SingleOwnerAccount::new(
-            starknet_rpc.clone(),
+            self.starknet_rpc.clone(),
             signer.clone(),
-            config.deployer.address,
-            config.setup.chain_id,
-            if config.deployer.legacy_account {
+            self.config.deployer.address,
+            self.chain_id,
+            if self.config.deployer.legacy_account {
                 ExecutionEncoding::Legacy
             } else {
                 ExecutionEncoding::New
@@ -62,13 +145,18 @@ impl GatlingSetup {
         );
         deployer.set_block_id(BlockId::Tag(BlockTag::Pending));
 
-        Ok(Self {
-            config,
-            starknet_rpc,
-            signer,
-            deployer,
-            accounts: vec![],
-        })
+        self.signer = Some(signer);
+        self.deployer = Some(deployer);
+
+        Ok(())
+    }
+
+    pub fn spec_version(&self) -> &str {
+        &self.spec_version
+    }
+
+    pub fn chain_id(&self) -> Felt {
+        self.chain_id
     }
 
     pub fn config(&self) -> &GatlingConfig {
@@ -79,14 +167,159 @@ impl GatlingSetup {
         &self.starknet_rpc
     }
 
+    /// Client used to check the transactions of an attack and gather block metrics
+    pub fn verification_client(&self) -> &Arc<JsonRpcClient<HttpTransport>> {
+        &self.verification_rpc
+    }
+
+    pub fn signer(&self) -> &GatlingSigner {
+        self.signer
+            .as_ref()
+            .expect("The deployer is loaded before sending transactions")
+    }
+
     pub fn deployer_account(&self) -> &StarknetAccount {
-        &self.deployer
+        self.deployer
+            .as_ref()
+            .expect("The deployer is loaded before sending transactions")
     }
 
     pub fn accounts(&self) -> &[StarknetAccount] {
         &self.accounts
     }
 
+    /// Refuse to send transactions to a public chain unless the user explicitly allowed it
+    pub fn check_public_chain(&self) -> Result<()> {
+        if !PUBLIC_CHAINS.contains(&self.chain_id) {
+            return Ok(());
+        }
+
+        ensure!(
+            self.config.run.allow_public_chains,
+            "Refusing to shoot transactions on the public chain {}, pass --i-know-what-im-doing to do it anyway",
+            short_string(self.chain_id)
+        );
+
+        tracing::warn!(
+            "Shooting transactions on the public chain {}",
+            short_string(self.chain_id)
+        );
+
+        Ok(())
+    }
+
+    /// Make sure the deployer account exists and holds enough fee tokens to fund the accounts
+    /// of the goose users not deployed yet and the counterfactual accounts of `shooters`,
+    /// along with the fees of the funding transfers
+    pub async fn check_deployer(&self, shooters: &[Shooters]) -> Result<()> {
+        let address = self.deployer_account().address();
+
+        match self
+            .starknet_rpc
+            .get_class_hash_at(BlockId::Tag(BlockTag::Pending), address)
+            .await
+        {
+            Ok(_) => {}
+            Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => {
+                bail!("Deployer account {address:#064x} does not exist on the chain")
+            }
+            Err(err) => return Err(eyre!(err)),
+        }
+
+        // Accounts deployed by a previous run are skipped by `create_accounts`
+        let mut unfunded = 0u64;
+        for account in self.derived_accounts().await? {
+            if self
+                .starknet_rpc
+                .get_class_hash_at(BlockId::Tag(BlockTag::Pending), account.address())
+                .await
+                .is_err()
+            {
+                unfunded += 1;
+            }
+        }
+
+        // Each funding transfer is a transaction of the deployer paying at most `MAX_FEE`
+        let accounts = Felt::from(unfunded) * (ACCOUNT_FUNDING + MAX_FEE);
+        let deployments = shooters
+            .iter()
+            .filter(|shooter| shooter.name == "deploy_account")
+            .map(|shooter| {
+                let transfers = shooter.shoot.div_ceil(FUNDING_CALLS_PER_TX as u64);
+                Felt::from(shooter.shoot) * MAX_FEE + Felt::from(transfers) * MAX_FEE
+            })
+            .fold(Felt::ZERO, |total, funding| total + funding);
+        let needed = accounts + deployments;
+
+        let balance = self.fee_token_balance(address).await?;
+
+        ensure!(
+            balance >= needed,
+            "Deployer account {address:#064x} holds {balance} fee tokens but the setup needs about {needed}"
+        );
+
+        Ok(())
+    }
+
+    /// Fee token balance of `address` in the pending block
+    pub async fn fee_token_balance(&self, address: Felt) -> Result<Felt> {
+        let balance = self
+            .starknet_rpc
+            .call(
+                FunctionCall {
+                    contract_address: self.config.setup.fee_token_address,
+                    entry_point_selector: selector!("balanceOf"),
+                    calldata: vec![address],
+                },
+                BlockId::Tag(BlockTag::Pending),
+            )
+            .await?;
+
+        // The balance is an u256 split in low and high felts
+        match balance.as_slice() {
+            [low, high] => Ok(*low + *high * Felt::TWO.pow(128u32)),
+            _ => bail!("Unexpected fee token balance {balance:?}"),
+        }
+    }
+
+    /// Accounts of the goose users as created by [`GatlingSetup::setup_accounts`],
+    /// derived from the deployer salt without deploying anything
+    pub async fn derived_accounts(&self) -> Result<Vec<StarknetAccount>> {
+        let account_contract = &self.config.setup.account_contract;
+        let class_hash = account_contract.get_class_hash()?;
+
+        let execution_encoding = match account_contract {
+            ContractSourceConfig::V0(_) => ExecutionEncoding::Legacy,
+            ContractSourceConfig::V1(_) => ExecutionEncoding::New,
+        };
+
+        let factory = OpenZeppelinAccountFactory::new(
+            class_hash,
+            self.chain_id,
+            self.signer(),
+            &self.starknet_rpc,
+        )
+        .await?;
+
+        let accounts = (0..self.config.run.concurrency)
+            .map(|i| {
+                let salt = self.config.deployer.salt + Felt::from(i);
+
+                let mut account = SingleOwnerAccount::new(
+                    self.starknet_rpc.clone(),
+                    self.signer().clone(),
+                    factory.deploy_v1(salt).address(),
+                    self.chain_id,
+                    execution_encoding,
+                );
+                account.set_block_id(BlockId::Tag(BlockTag::Pending));
+                account
+            })
+            .collect();
+
+        Ok(accounts)
+    }
+
     /// Setup the simulation.
     pub async fn setup_accounts(&mut self) -> Result<()> {
         let account_contract = self.config.setup.account_contract.clone();
@@ -141,21 +374,17 @@ impl GatlingSetup {
     ) -> Result<Vec<StarknetAccount>> {
         tracing::info!("Creating {} accounts", num_accounts);
 
-        let mut nonce = self.deployer.get_nonce().await?;
+        let mut nonce = self.deployer_account().get_nonce().await?;
         let mut deployed_accounts: Vec<StarknetAccount> = Vec::with_capacity(num_accounts);
 
         let mut deployment_joinset = JoinSet::new();
         for i in 0..num_accounts {
             // TODO: Check if OpenZepplinAccountFactory could be used with other type of accounts ? or should we require users to use OpenZepplinAccountFactory ?
-            let signer = self.signer.clone();
+            let signer = self.signer().clone();
             let provider = self.starknet_rpc.clone();
-            let account_factory = OpenZeppelinAccountFactory::new(
-                class_hash,
-                self.config.setup.chain_id,
-                &signer,
-                &provider,
-            )
-            .await?;
+            let account_factory =
+                OpenZeppelinAccountFactory::new(class_hash, self.chain_id, &signer, &provider)
+                    .await?;
 
             let salt = self.config.deployer.salt + Felt::from(i);
 
@@ -174,7 +403,7 @@ impl GatlingSetup {
                         self.starknet_rpc.clone(),
                         signer.clone(),
                         address,
-                        self.config.setup.chain_id,
+                        self.chain_id,
                         execution_encoding,
                     );
                     already_deployed_account.set_block_id(BlockId::Tag(BlockTag::Pending));
@@ -189,9 +418,9 @@ impl GatlingSetup {
             let tx_hash = self
                 .transfer(
                     fee_token_address,
-                    self.deployer.clone(),
+                    self.deployer_account().clone(),
                     address,
-                    felt!("0xFFFFFFFFFFFFFFF"),
+                    ACCOUNT_FUNDING,
                     nonce,
                 )
                 .await?;
@@ -204,7 +433,7 @@ impl GatlingSetup {
                 self.starknet_rpc.clone(),
                 signer.clone(),
                 result.contract_address,
-                self.config.setup.chain_id,
+                self.chain_id,
                 execution_encoding,
             );
             new_account.set_block_id(BlockId::Tag(BlockTag::Pending));
@@ -256,9 +485,9 @@ impl GatlingSetup {
             return Ok(class_hash);
         }
 
-        let nonce = self.deployer.get_nonce().await?;
+        let nonce = self.deployer_account().get_nonce().await?;
         let tx_resp = self
-            .deployer
+            .deployer_account()
             .declare_legacy(Arc::new(contract_artifact))
             .max_fee(MAX_FEE)
             .nonce(nonce)
@@ -291,19 +520,17 @@ impl GatlingSetup {
             contract_path.as_ref().display(),
             class_hash
         );
-        let nonce = self.deployer.get_nonce().await?;
+        let nonce = self.deployer_account().get_nonce().await?;
 
         if self.check_already_declared(class_hash).await? {
             return Ok(class_hash);
         }
 
-        self.deployer.set_block_id(BlockId::Tag(BlockTag::Pending));
-
         // We need to flatten the ABI into a string first
         let flattened_class = contract_artifact.flatten()?;
 
         let tx_resp = self
-            .deployer
+            .deployer_account()
             .declare_v2(Arc::new(flattened_class), casm_class_hash)
             .max_fee(MAX_FEE)
             .nonce(nonce)
@@ -367,6 +594,57 @@ pub async fn transfer(
     Ok(result.transaction_hash)
 }
 
+/// Make sure `url` serves the supported RPC spec version, switching it to a versioned path
+/// of the same node when it serves another one, and return the version it serves
+async fn supported_rpc_url(url: &mut String) -> Result<String> {
+    let version = starknet_rpc_provider(Url::parse(url)?)
+        .spec_version()
+        .await
+        .wrap_err_with(|| format!("Failed to get the RPC spec version of {url}"))?;
+
+    if is_supported(&version) {
+        return Ok(version);
+    }
+
+    for path in VERSIONED_PATHS {
+        let versioned = Url::parse(url)?.join(&format!("/{path}"))?;
+
+        if let Ok(served) = starknet_rpc_provider(versioned.clone())
+            .spec_version()
+            .await
+        {
+            if is_supported(&served) {
+                tracing::warn!(
+                    "{url} serves RPC spec {version}, using {versioned} serving {served} instead"
+                );
+                *url = versioned.into();
+                return Ok(served);
+            }
+        }
+    }
+
+    bail!(
+        "{url} serves RPC spec {version} but only {SUPPORTED_SPEC_VERSION} is supported, \
+        and none of the usual versioned paths serve it"
+    )
+}
+
+/// Chain ids are short strings, shown as hex when they are not
+fn short_string(felt: Felt) -> String {
+    parse_cairo_short_string(&felt).unwrap_or_else(|_| format!("{felt:#x}"))
+}
+
+fn is_supported(version: &str) -> bool {
+    is_compatible(version, SUPPORTED_SPEC_VERSION)
+}
+
+/// Whether `version` is `expected` or one of its patch versions
+pub fn is_compatible(version: &str, expected: &str) -> bool {
+    version
+        .strip_prefix(expected)
+        .is_some_and(|patch| patch.is_empty() || patch.starts_with('.'))
+}
+
 /// Create a StarkNet RPC provider from a URL.
 /// # Arguments
 /// * `rpc` - The URL of the StarkNet RPC provider.
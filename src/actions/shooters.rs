@@ -1,43 +1,101 @@
-use std::{boxed::Box, sync::Arc};
+use std::{
+    boxed::Box,
+    sync::{Arc, Mutex},
+};
 
 use color_eyre::eyre::OptionExt;
 use goose::{
     config::GooseConfiguration,
-    goose::{Scenario, Transaction, TransactionFunction},
+    goose::{GooseUser, Scenario, Transaction, TransactionFunction, TransactionResult},
     metrics::GooseMetrics,
     GooseAttack,
 };
 use starknet::{
+    accounts::ConnectedAccount,
     core::types::{Call, Felt, InvokeTransactionResult},
-    providers::jsonrpc::JsonRpcMethod,
+    providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse},
 };
 
 use crate::{
-    actions::goose::{send_execution, GooseWriteUserState},
-    config::GatlingConfig,
+    actions::{
+        goose::{
+            invoke_request, raw_send_batch, send_execution, signing_failure, Batch,
+            GooseWriteUserState,
+        },
+        setup::MAX_FEE,
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, GatlingConfig, Shooters},
+    metrics::BenchmarkReport,
 };
 
 use super::{
+    budget::Budget,
+    endpoints::Endpoints,
     goose::{
         goose_write_user_wait_last_tx, make_goose_config, setup, verify_transactions,
         TransactionBlocks,
     },
+    propagation::Tracker,
     setup::{GatlingSetup, StarknetAccount},
 };
 
+pub mod declare;
+pub mod deploy_account;
+pub mod events;
+pub mod invalid;
 pub mod mint;
+pub mod revert;
 pub mod transfer;
+pub mod udc;
+pub mod workload;
 
 pub struct ShooterAttack {
     pub goose_metrics: GooseMetrics,
     pub first_block: u64,
     pub last_block: u64,
+    /// Hashes of all the transactions that went through verification
+    pub transactions: Vec<Felt>,
+}
+
+/// Wrappers around each run of the submission transaction
+#[derive(Default)]
+pub struct SubmissionHooks {
+    pub tracker: Option<Arc<Tracker>>,
+    pub budget: Option<Arc<Budget>>,
+}
+
+impl SubmissionHooks {
+    fn wrap(&self, mut submission: TransactionFunction) -> TransactionFunction {
+        if let Some(tracker) = &self.tracker {
+            submission = tracker.track(submission);
+        }
+
+        // Outermost so that nothing is submitted nor tracked once over budget
+        if let Some(budget) = &self.budget {
+            submission = budget.enforce(submission);
+        }
+
+        submission
+    }
 }
 
 pub trait Shooter {
     const NAME: &'static str;
 
-    async fn setup(setup: &mut GatlingSetup) -> color_eyre::Result<Self>
+    /// Whether transactions of this shooter are meant to be included as reverted
+    const EXPECT_REVERTED: bool = false;
+
+    /// Whether transactions of this shooter can be submitted as JSON-RPC batches,
+    /// which is only the case for shooters sending their transactions through [`execute_invoke`]
+    const BATCHABLE: bool = true;
+
+    /// Check the options of `shooter` this shooter relies on, without sending anything to the node
+    fn validate(_config: &GatlingConfig, _shooter: &Shooters) -> color_eyre::Result<()> {
+        Ok(())
+    }
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self>
     where
         Self: Sized;
 
@@ -52,21 +110,34 @@ pub trait Shooter {
         self: Arc<Self>,
         config: GooseConfiguration,
         accounts: Vec<StarknetAccount>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+        endpoints: Arc<Endpoints>,
+        hooks: SubmissionHooks,
     ) -> color_eyre::Result<ShooterAttack>
     where
         Self: Send + Sync + 'static,
     {
         let setup: TransactionFunction = setup(accounts, config.iterations).await?;
 
-        let submission: TransactionFunction = Self::execute(self.clone());
+        let submission: TransactionFunction =
+            hooks.wrap(endpoints.distribute(Self::execute(self.clone(), calls_per_tx, batch)));
 
-        let finalizing: TransactionFunction = goose_write_user_wait_last_tx();
+        let finalizing: TransactionFunction =
+            endpoints.verify(goose_write_user_wait_last_tx(Self::EXPECT_REVERTED));
 
         let blocks: Arc<TransactionBlocks> = Arc::default();
         let blocks_cloned = blocks.clone();
 
-        let verify_transactions = Transaction::new(Arc::new(move |user| {
-            Box::pin(verify_transactions(user, blocks_cloned.clone()))
+        let transactions: Arc<Mutex<Vec<Felt>>> = Arc::default();
+        let transactions_cloned = transactions.clone();
+
+        let verify_transactions: TransactionFunction = endpoints.verify(Arc::new(move |user| {
+            Box::pin(verify_transactions(
+                user,
+                blocks_cloned.clone(),
+                transactions_cloned.clone(),
+            ))
         }));
 
         let goose_attack = GooseAttack::initialize_with_config(config)?.register_scenario(
@@ -84,7 +155,7 @@ pub trait Shooter {
                         .set_on_stop(),
                 )
                 .register_transaction(
-                    verify_transactions
+                    Transaction::new(verify_transactions)
                         .set_name("Verification")
                         .set_sequence(3)
                         .set_on_stop(),
@@ -97,50 +168,180 @@ pub trait Shooter {
             "Transaction blocks arc has multiple references after goose verification",
         )?;
 
+        let transactions = Arc::into_inner(transactions)
+            .ok_or_eyre("Transactions arc has multiple references after goose verification")?
+            .into_inner()
+            .expect("Sent transactions lock is never poisoned");
+
         Ok(ShooterAttack {
             goose_metrics: metrics,
             first_block: blocks.first.into_inner(),
             last_block: blocks.last.into_inner(),
+            transactions,
         })
     }
 
-    fn execute(self: Arc<Self>) -> TransactionFunction
+    /// Submission transaction of the attack, invoke shooters submit theirs with [`execute_invoke`]
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
     where
-        Self: Send + Sync + 'static,
-    {
-        Arc::new(move |user| {
-            let shooter = self.clone();
-
-            Box::pin(async move {
-                let GooseWriteUserState { account, nonce, .. } = user
-                    .get_session_data::<GooseWriteUserState>()
-                    .expect("Should be in a goose user with GooseUserState session data");
-
-                let call = shooter.get_execution_data(account);
-
-                let response: InvokeTransactionResult = send_execution(
-                    user,
-                    vec![call],
-                    *nonce,
-                    &account.clone(),
-                    JsonRpcMethod::AddInvokeTransaction,
-                )
-                .await?
-                .0;
+        Self: Send + Sync + 'static;
+
+    /// Records the contracts used by this shooter for later read benchmarks
+    fn artifacts(&self, _artifacts: &mut Artifacts) {}
 
-                let GooseWriteUserState { nonce, prev_tx, .. } =
-                    user.get_session_data_mut::<GooseWriteUserState>().expect(
-                        "Should be successful as we already asserted that the session data is a GooseUserState",
-                    );
+    /// Adds shooter specific metrics to the report once the attack is over
+    async fn report(
+        &self,
+        _setup: &GatlingSetup,
+        _attack: &ShooterAttack,
+        _report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        Ok(())
+    }
+}
+
+/// A shooter sending invoke transactions made of the calls it builds
+pub trait InvokeShooter: Shooter {
+    /// Call sent by the shooter, drawn anew for every call of every transaction
+    fn get_execution_data(&self, account: &StarknetAccount) -> Call;
+
+    /// Calls bundled in a single transaction, `amount` is drawn from `calls_per_tx`
+    fn get_execution_calls(&self, account: &StarknetAccount, amount: u64) -> Vec<Call> {
+        (0..amount)
+            .map(|_| self.get_execution_data(account))
+            .collect()
+    }
+}
 
-                *nonce += Felt::ONE;
+/// Submission transaction of an invoke shooter, sending a whole JSON-RPC batch per run with `batch`
+pub fn execute_invoke<S: InvokeShooter + Send + Sync + 'static>(
+    shooter: Arc<S>,
+    calls_per_tx: CallsPerTx,
+    batch: Option<Arc<Batch>>,
+) -> TransactionFunction {
+    Arc::new(move |user| {
+        let shooter = shooter.clone();
 
-                prev_tx.push(response.transaction_hash);
+        if let Some(batch) = batch.clone() {
+            return Box::pin(execute_batch(shooter, user, calls_per_tx, batch));
+        }
 
-                Ok(())
-            })
+        Box::pin(async move {
+            let GooseWriteUserState { account, nonce, .. } = user
+                .get_session_data::<GooseWriteUserState>()
+                .expect("Should be in a goose user with GooseUserState session data");
+
+            let calls = shooter.get_execution_calls(account, calls_per_tx.sample());
+
+            let response: InvokeTransactionResult = send_execution(
+                user,
+                calls,
+                *nonce,
+                &account.clone(),
+                JsonRpcMethod::AddInvokeTransaction,
+            )
+            .await?
+            .0;
+
+            let GooseWriteUserState { nonce, prev_tx, .. } =
+                user.get_session_data_mut::<GooseWriteUserState>().expect(
+                    "Should be successful as we already asserted that the session data is a GooseUserState",
+                );
+
+            *nonce += Felt::ONE;
+
+            prev_tx.push(response.transaction_hash);
+
+            Ok(())
         })
+    })
+}
+
+/// Submit `batch.size` transactions with consecutive nonces as a single JSON-RPC batch
+async fn execute_batch<S: InvokeShooter + ?Sized>(
+    shooter: Arc<S>,
+    user: &mut GooseUser,
+    calls_per_tx: CallsPerTx,
+    batch: Arc<Batch>,
+) -> TransactionResult {
+    let GooseWriteUserState { account, nonce, .. } = user
+        .get_session_data::<GooseWriteUserState>()
+        .expect("Should be in a goose user with GooseUserState session data");
+
+    let (account, nonce) = (account.clone(), *nonce);
+
+    let mut requests = Vec::with_capacity(batch.size as usize);
+
+    for index in 0..batch.size {
+        let calls = shooter.get_execution_calls(&account, calls_per_tx.sample());
+        let request = invoke_request(calls, nonce + Felt::from(index), MAX_FEE, &account)
+            .await
+            .map_err(|error| signing_failure(user, "Batch item could not be signed", &error))?;
+        requests.push([request]);
     }
 
-    fn get_execution_data(&self, account: &StarknetAccount) -> Call;
+    let (responses, mut metric) = raw_send_batch::<InvokeTransactionResult>(
+        user,
+        JsonRpcMethod::AddInvokeTransaction,
+        requests,
+    )
+    .await?;
+
+    batch.record(metric.response_time, &responses);
+
+    // Transactions after a rejected one are left with a nonce gap, so they are not
+    // tracked and the nonce only moves past the accepted transactions before it
+    let accepted = responses
+        .iter()
+        .take_while(|response| matches!(response, JsonRpcResponse::Success { .. }))
+        .count();
+
+    let mut accepted_hashes = Vec::with_capacity(accepted);
+    let mut first_error = None;
+
+    for (index, response) in responses.into_iter().enumerate() {
+        match response {
+            JsonRpcResponse::Success { result, .. } if index < accepted => {
+                accepted_hashes.push(result.transaction_hash);
+            }
+            JsonRpcResponse::Success { result, .. } => {
+                tracing::warn!(
+                    "Batch item {index} ({:#064x}) was accepted after a rejected item",
+                    result.transaction_hash
+                );
+            }
+            JsonRpcResponse::Error { error, .. } => {
+                first_error.get_or_insert((index, error));
+            }
+        }
+    }
+
+    let mut next_nonce = nonce + Felt::from(accepted);
+
+    if first_error.is_some() {
+        // The node knows best which nonces are taken after a partial rejection
+        match account.get_nonce().await {
+            Ok(nonce) => next_nonce = nonce,
+            Err(error) => tracing::warn!("Failed to resync the nonce after a rejection: {error}"),
+        }
+    }
+
+    let GooseWriteUserState { nonce, prev_tx, .. } =
+        user.get_session_data_mut::<GooseWriteUserState>().expect(
+            "Should be successful as we already asserted that the session data is a GooseUserState",
+        );
+
+    *nonce = next_nonce;
+    prev_tx.extend(accepted_hashes);
+
+    if let Some((index, error)) = first_error {
+        let tag = format!("Batch item {index} was rejected");
+        return user.set_failure(&tag, &mut metric, None, Some(&error.to_string()));
+    }
+
+    Ok(())
 }
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::bail;
+use goose::goose::TransactionFunction;
+use serde_json::{json, Value};
+use starknet::{
+    accounts::Account,
+    core::types::{
+        contract::SierraClass, BroadcastedDeclareTransaction, BroadcastedDeclareTransactionV2,
+        DeclareTransactionResult, Felt, FlattenedSierraClass, Transaction,
+    },
+    providers::jsonrpc::JsonRpcMethod,
+    signers::Signer,
+};
+
+use crate::{
+    actions::{
+        goose::{send_request, signing_failure, Batch, GooseWriteUserState},
+        setup::{GatlingSetup, StarknetAccount, MAX_FEE},
+        signer::{GatlingSigner, SignerError},
+    },
+    config::{CallsPerTx, ContractSourceConfig, Shooters},
+    generators::get_rng,
+    metrics::BenchmarkReport,
+};
+
+use super::{Shooter, ShooterAttack};
+
+/// Declares a new unique class on every transaction,
+/// all of them derived from the same template Sierra class
+pub struct DeclareShooter {
+    pub template: FlattenedSierraClass,
+    pub template_abi: Vec<Value>,
+    pub compiled_class_hash: Felt,
+    pub signer: GatlingSigner,
+}
+
+impl Shooter for DeclareShooter {
+    const NAME: &'static str = "Declares";
+
+    const BATCHABLE: bool = false;
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        let contract = config
+            .contract
+            .as_ref()
+            .unwrap_or(&setup.config().setup.erc20_contract);
+
+        let ContractSourceConfig::V1(contract) = contract else {
+            bail!("Declare shooter needs a v1 contract to use as a template");
+        };
+
+        let file = std::fs::File::open(&contract.path)?;
+        let template = serde_json::from_reader::<_, SierraClass>(file)?.flatten()?;
+        let template_abi = serde_json::from_str(&template.abi)?;
+
+        tracing::info!(
+            "Declaring classes derived from the template at {}",
+            contract.path.display()
+        );
+
+        Ok(Self {
+            template,
+            template_abi,
+            compiled_class_hash: contract.get_casm_hash()?,
+            signer: setup.signer().clone(),
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        _calls_per_tx: CallsPerTx,
+        _batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        Arc::new(move |user| {
+            let shooter = self.clone();
+
+            Box::pin(async move {
+                let GooseWriteUserState { account, nonce, .. } = user
+                    .get_session_data::<GooseWriteUserState>()
+                    .expect("Should be in a goose user with GooseUserState session data");
+
+                let (account, nonce) = (account.clone(), *nonce);
+
+                let param = shooter
+                    .declaration(&account, nonce)
+                    .await
+                    .map_err(|error| {
+                        signing_failure(user, "Declaration could not be signed", &error)
+                    })?;
+
+                let response: DeclareTransactionResult =
+                    send_request(user, JsonRpcMethod::AddDeclareTransaction, param)
+                        .await?
+                        .0;
+
+                let GooseWriteUserState { nonce, prev_tx, .. } =
+                    user.get_session_data_mut::<GooseWriteUserState>().expect(
+                        "Should be successful as we already asserted that the session data is a GooseUserState",
+                    );
+
+                *nonce += Felt::ONE;
+
+                prev_tx.push(response.transaction_hash);
+
+                Ok(())
+            })
+        })
+    }
+
+    async fn report(
+        &self,
+        setup: &GatlingSetup,
+        attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        report
+            .with_per_block_count(
+                setup.verification_client(),
+                attack.first_block,
+                attack.last_block,
+                "Declares",
+                "declares/block",
+                |tx| matches!(tx, Transaction::Declare(_)) as u64,
+            )
+            .await
+    }
+}
+
+impl DeclareShooter {
+    /// Derive a class never seen by the node from the template, the ABI is part
+    /// of the class hash but not of the compiled class so a dummy entry is enough
+    fn unique_class(&self) -> FlattenedSierraClass {
+        let mut abi = self.template_abi.clone();
+        abi.push(json!({
+            "type": "struct",
+            "name": format!("gatling::declare::Unique{:#x}", get_rng()),
+            "members": []
+        }));
+
+        FlattenedSierraClass {
+            abi: Value::Array(abi).to_string(),
+            ..self.template.clone()
+        }
+    }
+
+    async fn declaration(
+        &self,
+        account: &StarknetAccount,
+        nonce: Felt,
+    ) -> Result<BroadcastedDeclareTransaction, SignerError> {
+        let class = Arc::new(self.unique_class());
+
+        let declaration = account
+            .declare_v2(class.clone(), self.compiled_class_hash)
+            .nonce(nonce)
+            .max_fee(MAX_FEE)
+            .prepared()
+            .expect("Nonce and max fee are set");
+
+        let signature = self
+            .signer
+            .sign_hash(&declaration.transaction_hash(false))
+            .await?;
+
+        Ok(BroadcastedDeclareTransaction::V2(
+            BroadcastedDeclareTransactionV2 {
+                sender_address: account.address(),
+                compiled_class_hash: self.compiled_class_hash,
+                max_fee: MAX_FEE,
+                signature: vec![signature.r, signature.s],
+                nonce,
+                contract_class: class,
+                is_query: false,
+            },
+        ))
+    }
+}
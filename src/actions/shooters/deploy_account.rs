@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use crossbeam_queue::ArrayQueue;
+use goose::goose::TransactionFunction;
+use starknet::{
+    accounts::{Account, AccountFactory, ConnectedAccount, OpenZeppelinAccountFactory},
+    core::types::{
+        BroadcastedDeployAccountTransaction, BroadcastedDeployAccountTransactionV1, Call,
+        DeployAccountTransactionResult, Felt, Transaction,
+    },
+    macros::{felt, selector},
+    providers::jsonrpc::JsonRpcMethod,
+    signers::Signer,
+};
+use tokio::task::JoinSet;
+
+use crate::{
+    actions::{
+        goose::{send_request, Batch, GooseWriteUserState},
+        setup::{GatlingSetup, CHECK_INTERVAL, MAX_FEE},
+    },
+    config::{CallsPerTx, Shooters},
+    generators::get_rng,
+    metrics::BenchmarkReport,
+    utils::wait_for_tx,
+};
+
+use super::{Shooter, ShooterAttack};
+
+/// How many funding transfers the deployer bundles in a single transaction
+pub const FUNDING_CALLS_PER_TX: usize = 100;
+
+/// Deploys a new account on every transaction, the counterfactual addresses
+/// are funded and the deployments signed ahead of the attack
+pub struct DeployAccountShooter {
+    pub deployments: ArrayQueue<BroadcastedDeployAccountTransaction>,
+}
+
+impl Shooter for DeployAccountShooter {
+    const NAME: &'static str = "Account Deployments";
+
+    const BATCHABLE: bool = false;
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        let class_hash = setup
+            .declare_contract(&setup.config().setup.account_contract.clone())
+            .await?;
+
+        // Matches the amount of transactions done by goose after truncation
+        let concurrency = setup.config().run.concurrency;
+        let amount = (config.shoot.div_euclid(concurrency) * concurrency) as usize;
+
+        let factory = OpenZeppelinAccountFactory::new(
+            class_hash,
+            setup.chain_id(),
+            setup.signer().clone(),
+            setup.rpc_client().clone(),
+        )
+        .await?;
+
+        tracing::info!("Signing {amount} account deployments");
+
+        let deployments = ArrayQueue::new(amount.max(1));
+        let mut addresses = Vec::with_capacity(amount);
+
+        for _ in 0..amount {
+            let salt = get_rng();
+
+            let deployment = factory
+                .deploy_v1(salt)
+                .nonce(Felt::ZERO)
+                .max_fee(MAX_FEE)
+                .prepared()
+                .expect("Nonce and max fee are set");
+
+            let signature = setup
+                .signer()
+                .sign_hash(&deployment.transaction_hash(false))
+                .await?;
+
+            addresses.push(deployment.address());
+            deployments
+                .push(BroadcastedDeployAccountTransaction::V1(
+                    BroadcastedDeployAccountTransactionV1 {
+                        max_fee: MAX_FEE,
+                        signature: vec![signature.r, signature.s],
+                        nonce: Felt::ZERO,
+                        contract_address_salt: salt,
+                        constructor_calldata: factory.calldata(),
+                        class_hash,
+                        is_query: false,
+                    },
+                ))
+                .expect("Queue was created with enough space for all deployments");
+        }
+
+        Self::fund_addresses(setup, &addresses).await?;
+
+        Ok(Self { deployments })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        _calls_per_tx: CallsPerTx,
+        _batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        Arc::new(move |user| {
+            let shooter = self.clone();
+
+            Box::pin(async move {
+                let param = shooter.deployments.pop().expect(
+                    "Not enough account deployments were signed for the amount of transactions",
+                );
+
+                let response: DeployAccountTransactionResult =
+                    send_request(user, JsonRpcMethod::AddDeployAccountTransaction, param)
+                        .await?
+                        .0;
+
+                user.get_session_data_mut::<GooseWriteUserState>()
+                    .expect("Should be in a goose user with GooseUserState session data")
+                    .prev_tx
+                    .push(response.transaction_hash);
+
+                Ok(())
+            })
+        })
+    }
+
+    async fn report(
+        &self,
+        setup: &GatlingSetup,
+        attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        report
+            .with_per_block_count(
+                setup.verification_client(),
+                attack.first_block,
+                attack.last_block,
+                "Account Deployments",
+                "deployments/block",
+                |tx| matches!(tx, Transaction::DeployAccount(_)) as u64,
+            )
+            .await
+    }
+}
+
+impl DeployAccountShooter {
+    /// Transfer enough fee tokens to pay for the deployment to each address,
+    /// bundling many transfers in each deployer transaction
+    async fn fund_addresses(setup: &GatlingSetup, addresses: &[Felt]) -> color_eyre::Result<()> {
+        let deployer = setup.deployer_account();
+        let fee_token_address = setup.config().setup.fee_token_address;
+        let mut nonce = deployer.get_nonce().await?;
+
+        tracing::info!(
+            "Funding {} counterfactual account addresses",
+            addresses.len()
+        );
+
+        let mut joinset = JoinSet::new();
+
+        for chunk in addresses.chunks(FUNDING_CALLS_PER_TX) {
+            let calls = chunk
+                .iter()
+                .map(|&address| Call {
+                    to: fee_token_address,
+                    selector: selector!("transfer"),
+                    calldata: vec![address, MAX_FEE, felt!("0")],
+                })
+                .collect();
+
+            let result = deployer
+                .execute_v1(calls)
+                .max_fee(MAX_FEE)
+                .nonce(nonce)
+                .send()
+                .await?;
+
+            nonce += Felt::ONE;
+            let rpc_client = setup.rpc_client().clone();
+            joinset.spawn(async move {
+                wait_for_tx(&rpc_client, result.transaction_hash, CHECK_INTERVAL).await
+            });
+        }
+
+        while let Some(result) = joinset.join_next().await {
+            result??;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Instant,
+};
+
+use color_eyre::eyre::{bail, OptionExt};
+use goose::goose::TransactionFunction;
+use starknet::{
+    core::types::{BlockId, Call, EventFilter, Felt},
+    macros::selector,
+    providers::Provider,
+};
+
+use crate::{
+    actions::{
+        goose::Batch,
+        setup::{GatlingSetup, StarknetAccount},
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, GatlingConfig, Shooters},
+    metrics::{BenchmarkReport, MetricResult, GOOSE_TIME_UNIT},
+    utils::{get_receipts, receipt_events},
+};
+
+use super::{
+    execute_invoke,
+    workload::{Events, WorkloadShooter},
+    InvokeShooter, Shooter, ShooterAttack,
+};
+
+/// Page sizes used when the shooter does not configure them
+const DEFAULT_CHUNK_SIZES: [u64; 2] = [100, 1000];
+
+/// Page sizes configured for the shooter
+fn chunk_sizes(config: &Shooters) -> color_eyre::Result<Vec<u64>> {
+    let chunk_sizes = config
+        .chunk_sizes
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CHUNK_SIZES.to_vec());
+
+    if chunk_sizes.is_empty() || chunk_sizes.contains(&0) {
+        bail!("Events shooter chunk sizes must be a non empty list of positive sizes");
+    }
+
+    Ok(chunk_sizes)
+}
+
+/// Key of the events emitted by the workload contract
+const PING_KEY: Felt = selector!("Ping");
+
+/// Events are told apart by their transaction, keys and data, which holds their index
+type EventId = (Felt, Vec<Felt>, Vec<Felt>);
+
+/// Emits a known amount of events with known keys, then checks that
+/// `starknet_getEvents` returns every one of them whatever the page size
+pub struct EventsShooter {
+    pub workload: WorkloadShooter<Events>,
+    pub chunk_sizes: Vec<u64>,
+}
+
+impl Shooter for EventsShooter {
+    const NAME: &'static str = "Event Emissions";
+
+    fn validate(config: &GatlingConfig, shooter: &Shooters) -> color_eyre::Result<()> {
+        chunk_sizes(shooter)?;
+        WorkloadShooter::<Events>::validate(config, shooter)
+    }
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        Ok(Self {
+            chunk_sizes: chunk_sizes(config)?,
+            workload: WorkloadShooter::setup(setup, config).await?,
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    fn artifacts(&self, artifacts: &mut Artifacts) {
+        self.workload.artifacts(artifacts)
+    }
+
+    async fn report(
+        &self,
+        setup: &GatlingSetup,
+        attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        let receipts = get_receipts(setup.verification_client(), &attack.transactions).await?;
+
+        let mut expected: BTreeMap<EventId, u64> = BTreeMap::new();
+        let mut callers = BTreeSet::new();
+
+        for receipt in &receipts {
+            let transaction_hash = *receipt.receipt.transaction_hash();
+
+            for event in receipt_events(&receipt.receipt) {
+                if event.from_address != self.workload.contract_address
+                    || event.keys.first() != Some(&PING_KEY)
+                {
+                    continue;
+                }
+
+                if let Some(&caller) = event.keys.get(1) {
+                    callers.insert(caller);
+                }
+
+                *expected
+                    .entry((transaction_hash, event.keys.clone(), event.data.clone()))
+                    .or_default() += 1;
+            }
+        }
+
+        let block_numbers = receipts.iter().filter_map(|r| r.block.block_number());
+        let start_block = block_numbers
+            .clone()
+            .min()
+            .ok_or_eyre("No events were emitted")?;
+        let end_block = block_numbers.max().ok_or_eyre("No events were emitted")?;
+
+        let emitted: u64 = expected.values().sum();
+
+        report.metrics.push(MetricResult {
+            name: "getEvents Expected Events".into(),
+            unit: "events",
+            value: emitted.into(),
+        });
+
+        let mut total_missing = 0;
+
+        for &chunk_size in &self.chunk_sizes {
+            let (returned, pages, time) = self
+                .get_events(
+                    setup,
+                    start_block,
+                    end_block,
+                    vec![vec![PING_KEY]],
+                    chunk_size,
+                )
+                .await?;
+
+            let missing = count_missing(&expected, returned);
+            total_missing += missing;
+
+            report.metrics.extend_from_slice(&[
+                MetricResult {
+                    name: format!("getEvents Chunk {chunk_size} Pages").into(),
+                    unit: "pages",
+                    value: pages.into(),
+                },
+                MetricResult {
+                    name: format!("getEvents Chunk {chunk_size} Average Page Time").into(),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (time as f64 / pages as f64).into(),
+                },
+                MetricResult {
+                    name: format!("getEvents Chunk {chunk_size} Missing Events").into(),
+                    unit: "events",
+                    value: missing.into(),
+                },
+            ]);
+        }
+
+        // Filtering on the caller key must return exactly the events of that caller
+        let chunk_size = *self
+            .chunk_sizes
+            .iter()
+            .max()
+            .expect("Chunk sizes are not empty");
+        let mut caller_missing = 0;
+
+        for &caller in &callers {
+            let (returned, ..) = self
+                .get_events(
+                    setup,
+                    start_block,
+                    end_block,
+                    vec![vec![PING_KEY], vec![caller]],
+                    chunk_size,
+                )
+                .await?;
+
+            let of_caller = expected
+                .iter()
+                .filter(|((_, keys, _), _)| keys.get(1) == Some(&caller))
+                .map(|(id, amount)| (id.clone(), *amount))
+                .collect();
+
+            let unexpected = returned
+                .iter()
+                .filter(|(_, keys, _)| keys.get(1) != Some(&caller))
+                .count() as u64;
+
+            caller_missing += count_missing(&of_caller, returned) + unexpected;
+        }
+
+        report.metrics.push(MetricResult {
+            name: "getEvents Key Filtered Missing Events".into(),
+            unit: "events",
+            value: caller_missing.into(),
+        });
+
+        if total_missing + caller_missing != 0 {
+            bail!(
+                "starknet_getEvents did not return {} of the {emitted} emitted events",
+                total_missing + caller_missing
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl InvokeShooter for EventsShooter {
+    fn get_execution_data(&self, account: &StarknetAccount) -> Call {
+        self.workload.get_execution_data(account)
+    }
+}
+
+impl EventsShooter {
+    /// Page through all the events of the workload contract matching `keys`,
+    /// returning them along with the amount of pages and the total time taken
+    async fn get_events(
+        &self,
+        setup: &GatlingSetup,
+        start_block: u64,
+        end_block: u64,
+        keys: Vec<Vec<Felt>>,
+        chunk_size: u64,
+    ) -> color_eyre::Result<(Vec<EventId>, u64, u64)> {
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(start_block)),
+            to_block: Some(BlockId::Number(end_block)),
+            address: Some(self.workload.contract_address),
+            keys: Some(keys),
+        };
+
+        let mut events = Vec::new();
+        let mut continuation_token = None;
+        let (mut pages, mut time) = (0, 0);
+
+        loop {
+            let start = Instant::now();
+            let page = setup
+                .verification_client()
+                .get_events(filter.clone(), continuation_token, chunk_size)
+                .await?;
+            time += start.elapsed().as_millis() as u64;
+            pages += 1;
+
+            if page.events.len() as u64 > chunk_size {
+                bail!(
+                    "starknet_getEvents returned {} events for a chunk size of {chunk_size}",
+                    page.events.len()
+                );
+            }
+
+            events.extend(
+                page.events
+                    .into_iter()
+                    .map(|event| (event.transaction_hash, event.keys, event.data)),
+            );
+
+            match page.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok((events, pages, time))
+    }
+}
+
+/// Amount of expected events that were not returned
+fn count_missing(expected: &BTreeMap<EventId, u64>, returned: Vec<EventId>) -> u64 {
+    let mut remaining = expected.clone();
+
+    for id in returned {
+        if let Some(amount) = remaining.get_mut(&id) {
+            *amount = amount.saturating_sub(1);
+        }
+    }
+
+    remaining.values().sum()
+}
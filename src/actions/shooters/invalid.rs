@@ -0,0 +1,325 @@
+use std::{collections::BTreeMap, sync::Arc, sync::Mutex};
+
+use color_eyre::eyre::ensure;
+use goose::goose::TransactionFunction;
+use rand::distributions::{Distribution, WeightedIndex};
+use starknet::{
+    accounts::Account,
+    core::types::{BroadcastedInvokeTransaction, Call, Felt, InvokeTransactionResult},
+    macros::{felt, selector},
+    providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse},
+};
+
+use crate::{
+    actions::{
+        goose::{invoke_request, raw_send_request, signing_failure, Batch, GooseWriteUserState},
+        setup::{GatlingSetup, StarknetAccount, MAX_FEE},
+    },
+    config::{CallsPerTx, GatlingConfig, InvalidTransactionKind, Shooters},
+    metrics::{BenchmarkReport, MetricResult, GOOSE_TIME_UNIT},
+};
+
+use super::{InvokeShooter, Shooter, ShooterAttack};
+
+/// How far ahead of the account nonce future nonces are
+const FUTURE_NONCE_OFFSET: u64 = 1000;
+
+/// Amount of extra field elements appended to the calldata of oversized transactions
+const OVERSIZED_CALLDATA_LEN: usize = 10_000;
+
+/// Sends a weighted mix of valid and malformed transactions,
+/// classifying how and how fast the node answers each kind
+pub struct InvalidShooter {
+    pub fee_token_address: Felt,
+    pub kinds: Vec<InvalidTransactionKind>,
+    pub weights: WeightedIndex<u64>,
+    pub stats: Mutex<BTreeMap<InvalidTransactionKind, KindStats>>,
+}
+
+#[derive(Debug, Default)]
+pub struct KindStats {
+    pub accepted: u64,
+    pub accepted_time: u64,
+    pub rejected: u64,
+    pub rejected_time: u64,
+    /// Amount of rejections and message per JSON-RPC error code
+    pub errors: BTreeMap<i64, (String, u64)>,
+}
+
+impl Shooter for InvalidShooter {
+    const NAME: &'static str = "Invalid Transactions";
+
+    const BATCHABLE: bool = false;
+
+    fn validate(_config: &GatlingConfig, shooter: &Shooters) -> color_eyre::Result<()> {
+        mix(shooter).map(drop)
+    }
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        let (kinds, weights) = mix(config)?;
+
+        Ok(Self {
+            fee_token_address: setup.config().setup.fee_token_address,
+            kinds,
+            weights,
+            stats: Mutex::default(),
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        _calls_per_tx: CallsPerTx,
+        _batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        Arc::new(move |user| {
+            let shooter = self.clone();
+            let kind = shooter.kinds[shooter.weights.sample(&mut rand::thread_rng())];
+
+            Box::pin(async move {
+                let GooseWriteUserState { account, nonce, .. } = user
+                    .get_session_data::<GooseWriteUserState>()
+                    .expect("Should be in a goose user with GooseUserState session data");
+
+                let (account, nonce) = (account.clone(), *nonce);
+
+                let request = shooter
+                    .request(kind, &account, nonce)
+                    .await
+                    .map_err(|error| {
+                        signing_failure(user, "Invalid transaction could not be signed", &error)
+                    })?;
+
+                let (response, metric) = raw_send_request::<InvokeTransactionResult>(
+                    user,
+                    JsonRpcMethod::AddInvokeTransaction,
+                    request,
+                )
+                .await?;
+
+                match response {
+                    JsonRpcResponse::Success { result, .. } => {
+                        shooter.record(kind, metric.response_time, None);
+
+                        let GooseWriteUserState { nonce, prev_tx, .. } =
+                            user.get_session_data_mut::<GooseWriteUserState>().expect(
+                                "Should be successful as we already asserted that the session data is a GooseUserState",
+                            );
+
+                        *nonce = next_nonce(kind, *nonce, Some(result.transaction_hash));
+
+                        // Only valid transactions are expected to succeed during verification
+                        if kind == InvalidTransactionKind::Valid {
+                            prev_tx.push(result.transaction_hash);
+                        }
+
+                        Ok(())
+                    }
+                    JsonRpcResponse::Error { error, .. } => {
+                        shooter.record(
+                            kind,
+                            metric.response_time,
+                            Some((error.code, &error.message)),
+                        );
+
+                        if kind == InvalidTransactionKind::Valid {
+                            let mut metric = metric;
+                            return user.set_failure(
+                                "Valid transaction was rejected",
+                                &mut metric,
+                                None,
+                                Some(&error.to_string()),
+                            );
+                        }
+
+                        Ok(())
+                    }
+                }
+            })
+        })
+    }
+
+    async fn report(
+        &self,
+        _setup: &GatlingSetup,
+        _attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        let stats = self.stats.lock().expect("Stats lock is never poisoned");
+
+        for (kind, stats) in stats.iter() {
+            let name = kind.name();
+
+            report.metrics.extend_from_slice(&[
+                MetricResult {
+                    name: format!("{name} Accepted").into(),
+                    unit: "",
+                    value: stats.accepted.into(),
+                },
+                MetricResult {
+                    name: format!("{name} Rejected").into(),
+                    unit: "",
+                    value: stats.rejected.into(),
+                },
+            ]);
+
+            if stats.accepted != 0 {
+                report.metrics.push(MetricResult {
+                    name: format!("{name} Average Acceptance Time").into(),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (stats.accepted_time as f64 / stats.accepted as f64).into(),
+                });
+            }
+
+            if stats.rejected != 0 {
+                report.metrics.push(MetricResult {
+                    name: format!("{name} Average Rejection Time").into(),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (stats.rejected_time as f64 / stats.rejected as f64).into(),
+                });
+            }
+
+            for (code, (message, amount)) in &stats.errors {
+                report.metrics.push(MetricResult {
+                    name: format!("{name} Error {code} ({message})").into(),
+                    unit: "",
+                    value: (*amount).into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InvokeShooter for InvalidShooter {
+    fn get_execution_data(&self, _account: &StarknetAccount) -> Call {
+        const VOID_ADDRESS: Felt = felt!("0xdead");
+        let (amount_low, amount_high) = (felt!("1"), felt!("0"));
+        Call {
+            to: self.fee_token_address,
+            selector: selector!("transfer"),
+            calldata: vec![VOID_ADDRESS, amount_low, amount_high],
+        }
+    }
+}
+
+/// Kinds of transactions the shooter sends along with their weights,
+/// every kind being equally likely when the shooter does not configure them
+fn mix(config: &Shooters) -> color_eyre::Result<(Vec<InvalidTransactionKind>, WeightedIndex<u64>)> {
+    let mix = config.mix.clone().unwrap_or_else(|| {
+        InvalidTransactionKind::ALL
+            .into_iter()
+            .map(|kind| (kind, 1))
+            .collect()
+    });
+
+    ensure!(
+        mix.values().any(|&weight| weight > 0),
+        "Invalid shooter mix needs at least one kind with a positive weight"
+    );
+
+    let (kinds, weights): (Vec<_>, Vec<_>) = mix.into_iter().unzip();
+
+    Ok((kinds, WeightedIndex::new(weights)?))
+}
+
+/// Nonce of the account once a transaction of `kind` was sent with `nonce`, only the transactions
+/// the node returned a hash for consume it, as long as they were sent with it
+fn next_nonce(kind: InvalidTransactionKind, nonce: Felt, transaction_hash: Option<Felt>) -> Felt {
+    match (kind, transaction_hash) {
+        (InvalidTransactionKind::StaleNonce | InvalidTransactionKind::FutureNonce, _)
+        | (_, None) => nonce,
+        (_, Some(_)) => nonce + Felt::ONE,
+    }
+}
+
+impl InvalidShooter {
+    async fn request(
+        &self,
+        kind: InvalidTransactionKind,
+        account: &StarknetAccount,
+        nonce: Felt,
+    ) -> Result<BroadcastedInvokeTransaction, <StarknetAccount as Account>::SignError> {
+        let mut call = self.get_execution_data(account);
+        let mut max_fee = MAX_FEE;
+        let mut nonce = nonce;
+
+        match kind {
+            InvalidTransactionKind::Valid | InvalidTransactionKind::InvalidSignature => {}
+            // Load accounts are deployed, so their nonce is never zero
+            InvalidTransactionKind::StaleNonce => nonce -= Felt::ONE,
+            InvalidTransactionKind::FutureNonce => nonce += Felt::from(FUTURE_NONCE_OFFSET),
+            InvalidTransactionKind::InsufficientMaxFee => max_fee = Felt::ONE,
+            InvalidTransactionKind::UnknownSelector => {
+                call.selector = selector!("gatling_unknown_selector")
+            }
+            InvalidTransactionKind::OversizedCalldata => call
+                .calldata
+                .extend(std::iter::repeat(Felt::ONE).take(OVERSIZED_CALLDATA_LEN)),
+        }
+
+        let mut request = invoke_request(vec![call], nonce, max_fee, account).await?;
+
+        if kind == InvalidTransactionKind::InvalidSignature {
+            let BroadcastedInvokeTransaction::V1(request) = &mut request else {
+                unreachable!("Invoke requests are built as V1")
+            };
+
+            if let Some(signature) = request.signature.first_mut() {
+                *signature += Felt::ONE;
+            }
+        }
+
+        Ok(request)
+    }
+
+    fn record(&self, kind: InvalidTransactionKind, response_time: u64, error: Option<(i64, &str)>) {
+        let mut stats = self.stats.lock().expect("Stats lock is never poisoned");
+        let stats = stats.entry(kind).or_default();
+
+        match error {
+            None => {
+                stats.accepted += 1;
+                stats.accepted_time += response_time;
+            }
+            Some((code, message)) => {
+                stats.rejected += 1;
+                stats.rejected_time += response_time;
+                stats
+                    .errors
+                    .entry(code)
+                    .or_insert_with(|| (message.to_string(), 0))
+                    .1 += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_accepted_transactions_sent_with_the_nonce_consume_it() {
+        let nonce = Felt::from(7);
+        let hash = Some(Felt::from(0x1234));
+
+        for kind in InvalidTransactionKind::ALL {
+            assert_eq!(next_nonce(kind, nonce, None), nonce, "{kind:?} rejected");
+
+            let expected = match kind {
+                InvalidTransactionKind::StaleNonce | InvalidTransactionKind::FutureNonce => nonce,
+                InvalidTransactionKind::Valid
+                | InvalidTransactionKind::InvalidSignature
+                | InvalidTransactionKind::InsufficientMaxFee
+                | InvalidTransactionKind::UnknownSelector
+                | InvalidTransactionKind::OversizedCalldata => nonce + Felt::ONE,
+            };
+
+            assert_eq!(next_nonce(kind, nonce, hash), expected, "{kind:?} accepted");
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, sync::Arc};
 
 use color_eyre::eyre::bail;
+use goose::goose::TransactionFunction;
 use starknet::{
     accounts::{Account, ConnectedAccount},
     contract::ContractFactory,
@@ -14,12 +15,17 @@ use starknet::{
 use tokio::task::JoinSet;
 
 use crate::{
-    actions::setup::{GatlingSetup, StarknetAccount, CHECK_INTERVAL, MAX_FEE},
+    actions::{
+        goose::Batch,
+        setup::{GatlingSetup, StarknetAccount, CHECK_INTERVAL, MAX_FEE},
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, Shooters},
     generators::get_rng,
     utils::wait_for_tx,
 };
 
-use super::Shooter;
+use super::{execute_invoke, InvokeShooter, Shooter};
 
 pub struct MintShooter {
     pub account_to_erc721_addresses: HashMap<Felt, Felt>,
@@ -30,7 +36,7 @@ pub struct MintShooter {
 impl Shooter for MintShooter {
     const NAME: &'static str = "Erc721 Mints";
 
-    async fn setup(setup: &mut GatlingSetup) -> color_eyre::Result<Self> {
+    async fn setup(setup: &mut GatlingSetup, _config: &Shooters) -> color_eyre::Result<Self> {
         let erc721_class_hash = setup
             .declare_contract(&setup.config().setup.erc721_contract.clone())
             .await?;
@@ -63,6 +69,25 @@ impl Shooter for MintShooter {
         })
     }
 
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    fn artifacts(&self, artifacts: &mut Artifacts) {
+        for &address in self.account_to_erc721_addresses.values() {
+            artifacts.add_contract("erc721", address);
+        }
+    }
+}
+
+impl InvokeShooter for MintShooter {
     fn get_execution_data(&self, account: &StarknetAccount) -> Call {
         let recipient = account.address();
 
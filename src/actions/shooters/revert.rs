@@ -0,0 +1,156 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use goose::goose::TransactionFunction;
+use starknet::{
+    core::types::{Call, ExecutionResult, Felt, PriceUnit},
+    macros::{felt, selector},
+};
+
+use crate::{
+    actions::{
+        goose::Batch,
+        setup::{GatlingSetup, StarknetAccount},
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, Shooters},
+    metrics::{BenchmarkReport, MetricResult},
+    utils::{get_receipts, receipt_actual_fee},
+};
+
+use super::{execute_invoke, transfer::TransferShooter, InvokeShooter, Shooter, ShooterAttack};
+
+/// Sends ERC20 transfers that do some work and then revert by
+/// transferring more than the balance of the account
+pub struct RevertShooter {
+    pub transfers: TransferShooter,
+    /// Amount of successful transfers done before the reverting one
+    pub work: u64,
+}
+
+impl Shooter for RevertShooter {
+    const NAME: &'static str = "Reverted Erc20 Transfers";
+
+    const EXPECT_REVERTED: bool = true;
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        Ok(Self {
+            transfers: TransferShooter::setup(setup, config).await?,
+            work: config.work.unwrap_or_default(),
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    fn artifacts(&self, artifacts: &mut Artifacts) {
+        self.transfers.artifacts(artifacts)
+    }
+
+    async fn report(
+        &self,
+        setup: &GatlingSetup,
+        attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        let receipts = get_receipts(setup.verification_client(), &attack.transactions).await?;
+
+        let mut per_block: BTreeMap<u64, u64> = (attack.first_block..=attack.last_block)
+            .map(|block| (block, 0))
+            .collect();
+        let mut reasons: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut fees: BTreeMap<&'static str, (f64, u64)> = BTreeMap::new();
+
+        for receipt in &receipts {
+            let ExecutionResult::Reverted { reason } = receipt.receipt.execution_result() else {
+                continue;
+            };
+
+            if let Some(block_number) = receipt.block.block_number() {
+                *per_block.entry(block_number).or_default() += 1;
+            }
+
+            // Revert reasons can span many lines of call traces, the last one holds the error
+            let reason = reason.trim().lines().last().unwrap_or_default().trim();
+            *reasons.entry(reason).or_default() += 1;
+
+            let fee = receipt_actual_fee(&receipt.receipt);
+            let unit = match fee.unit {
+                PriceUnit::Wei => "wei",
+                PriceUnit::Fri => "fri",
+            };
+            let (total, amount) = fees.entry(unit).or_default();
+            *total += u128::try_from(fee.amount)? as f64;
+            *amount += 1;
+        }
+
+        let reverted: u64 = per_block.values().sum();
+
+        report.metrics.push(MetricResult {
+            name: "Reverted Transactions".into(),
+            unit: "",
+            value: reverted.into(),
+        });
+
+        report.with_per_block_amounts(
+            "Reverted Transactions",
+            "transactions/block",
+            &per_block.into_values().collect::<Vec<_>>(),
+        );
+
+        for (unit, (total, amount)) in fees {
+            report.metrics.extend_from_slice(&[
+                MetricResult {
+                    name: "Total Reverted Fee".into(),
+                    unit,
+                    value: total.into(),
+                },
+                MetricResult {
+                    name: "Average Reverted Fee".into(),
+                    unit,
+                    value: (total / amount as f64).into(),
+                },
+            ]);
+        }
+
+        for (reason, amount) in reasons {
+            report.metrics.push(MetricResult {
+                name: format!("Revert Reason: {reason}").into(),
+                unit: "",
+                value: amount.into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl InvokeShooter for RevertShooter {
+    fn get_execution_data(&self, _account: &StarknetAccount) -> Call {
+        const VOID_ADDRESS: Felt = felt!("0xdead");
+        // Accounts are funded with far less than this during setup
+        let (amount_low, amount_high) = (felt!("0xFFFFFFFFFFFFFFFFFFFFFFFF"), felt!("0"));
+        Call {
+            to: self.transfers.erc20_address,
+            selector: selector!("transfer"),
+            calldata: vec![VOID_ADDRESS, amount_low, amount_high],
+        }
+    }
+
+    fn get_execution_calls(&self, account: &StarknetAccount, amount: u64) -> Vec<Call> {
+        (0..amount)
+            .flat_map(|_| {
+                (0..self.work)
+                    .map(|_| self.transfers.get_execution_data(account))
+                    .chain([self.get_execution_data(account)])
+            })
+            .collect()
+    }
+}
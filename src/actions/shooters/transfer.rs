@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use color_eyre::eyre::bail;
+use goose::goose::TransactionFunction;
 use starknet::{
     accounts::{Account, ConnectedAccount},
     contract::ContractFactory,
@@ -12,11 +15,16 @@ use starknet::{
 use tokio::task::JoinSet;
 
 use crate::{
-    actions::setup::{self, GatlingSetup, StarknetAccount, CHECK_INTERVAL, MAX_FEE},
+    actions::{
+        goose::Batch,
+        setup::{self, GatlingSetup, StarknetAccount, CHECK_INTERVAL, MAX_FEE},
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, Shooters},
     utils::wait_for_tx,
 };
 
-use super::Shooter;
+use super::{execute_invoke, InvokeShooter, Shooter};
 
 pub struct TransferShooter {
     pub erc20_address: Felt,
@@ -27,7 +35,7 @@ pub struct TransferShooter {
 impl Shooter for TransferShooter {
     const NAME: &'static str = "Erc20 Transfers";
 
-    async fn setup(setup: &mut GatlingSetup) -> color_eyre::Result<Self>
+    async fn setup(setup: &mut GatlingSetup, _config: &Shooters) -> color_eyre::Result<Self>
     where
         Self: Sized,
     {
@@ -129,6 +137,23 @@ impl Shooter for TransferShooter {
         })
     }
 
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    fn artifacts(&self, artifacts: &mut Artifacts) {
+        artifacts.add_contract("erc20", self.erc20_address);
+    }
+}
+
+impl InvokeShooter for TransferShooter {
     fn get_execution_data(&self, _account: &StarknetAccount) -> Call {
         const VOID_ADDRESS: Felt = felt!("0xdead");
         let (amount_low, amount_high) = (felt!("1"), felt!("0"));
@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use goose::goose::TransactionFunction;
+use starknet::{
+    accounts::Account,
+    core::types::{Call, Felt},
+    macros::{felt, selector},
+};
+
+use crate::{
+    actions::{
+        goose::Batch,
+        setup::{GatlingSetup, StarknetAccount},
+    },
+    config::{CallsPerTx, Shooters},
+    generators::get_rng,
+    metrics::BenchmarkReport,
+    utils::get_events_in_range,
+};
+
+use super::{execute_invoke, InvokeShooter, Shooter, ShooterAttack};
+
+/// The default Universal Deployer Contract address
+const UDC_ADDRESS: Felt =
+    felt!("0x041a78e741e5af2fec34b695679bc6891742439f7afb8484ecd7766661ad02bf");
+
+pub struct UdcShooter {
+    pub class_hash: Felt,
+    /// Falls back to the ERC721 constructor with the account as recipient when unset
+    pub constructor_calldata: Option<Vec<Felt>>,
+}
+
+impl Shooter for UdcShooter {
+    const NAME: &'static str = "UDC Deployments";
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        let (contract, constructor_calldata) = match &config.contract {
+            Some(contract) => (
+                contract.clone(),
+                Some(config.constructor_calldata.clone().unwrap_or_default()),
+            ),
+            None => (
+                setup.config().setup.erc721_contract.clone(),
+                config.constructor_calldata.clone(),
+            ),
+        };
+
+        let class_hash = setup.declare_contract(&contract).await?;
+
+        Ok(Self {
+            class_hash,
+            constructor_calldata,
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    async fn report(
+        &self,
+        setup: &GatlingSetup,
+        attack: &ShooterAttack,
+        report: &mut BenchmarkReport,
+    ) -> color_eyre::Result<()> {
+        let events = get_events_in_range(
+            setup.verification_client(),
+            attack.first_block,
+            attack.last_block,
+            Some(UDC_ADDRESS),
+            Some(vec![vec![selector!("ContractDeployed")]]),
+        )
+        .await?;
+
+        let mut per_block: BTreeMap<u64, u64> = (attack.first_block..=attack.last_block)
+            .map(|block| (block, 0))
+            .collect();
+
+        for event in events {
+            if let Some(block_number) = event.block_number {
+                *per_block.entry(block_number).or_default() += 1;
+            }
+        }
+
+        report.with_per_block_amounts(
+            "Contracts Deployed",
+            "contracts/block",
+            &per_block.into_values().collect::<Vec<_>>(),
+        );
+
+        Ok(())
+    }
+}
+
+impl InvokeShooter for UdcShooter {
+    fn get_execution_data(&self, account: &StarknetAccount) -> Call {
+        let constructor_calldata = match &self.constructor_calldata {
+            Some(calldata) => calldata.clone(),
+            None => vec![selector!("TestNFT"), selector!("TNFT"), account.address()],
+        };
+
+        // A random salt on a non unique deployment gives a fresh address every time
+        let mut calldata = vec![
+            self.class_hash,
+            get_rng(),
+            Felt::ZERO,
+            constructor_calldata.len().into(),
+        ];
+        calldata.extend(constructor_calldata);
+
+        Call {
+            to: UDC_ADDRESS,
+            selector: selector!("deployContract"),
+            calldata,
+        }
+    }
+}
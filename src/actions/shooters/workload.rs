@@ -0,0 +1,199 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use color_eyre::eyre::{bail, ensure, OptionExt};
+use goose::goose::TransactionFunction;
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    contract::ContractFactory,
+    core::{
+        types::{BlockId, BlockTag, Call, Felt},
+        utils::{get_udc_deployed_address, UdcUniqueness},
+    },
+    macros::selector,
+    providers::Provider,
+};
+
+use crate::{
+    actions::{
+        goose::Batch,
+        setup::{GatlingSetup, StarknetAccount, CHECK_INTERVAL, MAX_FEE},
+    },
+    artifacts::Artifacts,
+    config::{CallsPerTx, ContractSourceConfig, GatlingConfig, Shooters},
+    utils::wait_for_tx,
+};
+
+use super::{execute_invoke, InvokeShooter, Shooter};
+
+/// Amount of work done by each call when the shooter does not configure it
+const DEFAULT_WORK: u64 = 100;
+
+/// A single kind of work done by the synthetic workload contract
+pub trait Workload {
+    const NAME: &'static str;
+    /// Entrypoint of the workload contract doing this kind of work
+    const SELECTOR: Felt;
+}
+
+/// A tight arithmetic loop
+pub struct Compute;
+
+impl Workload for Compute {
+    const NAME: &'static str = "Compute Loops";
+    const SELECTOR: Felt = selector!("compute");
+}
+
+/// Writes to storage slots never written before
+pub struct Storage;
+
+impl Workload for Storage {
+    const NAME: &'static str = "Storage Writes";
+    const SELECTOR: Felt = selector!("write_storage");
+}
+
+/// Event emissions
+pub struct Events;
+
+impl Workload for Events {
+    const NAME: &'static str = "Event Emissions";
+    const SELECTOR: Felt = selector!("emit_events");
+}
+
+/// Chained Poseidon hashes
+pub struct Poseidon;
+
+impl Workload for Poseidon {
+    const NAME: &'static str = "Poseidon Hashes";
+    const SELECTOR: Felt = selector!("hash_poseidon");
+}
+
+/// Chained Pedersen hashes
+pub struct Pedersen;
+
+impl Workload for Pedersen {
+    const NAME: &'static str = "Pedersen Hashes";
+    const SELECTOR: Felt = selector!("hash_pedersen");
+}
+
+/// Calls an entrypoint of the synthetic workload contract,
+/// repeating its work `work` times in each call
+pub struct WorkloadShooter<W> {
+    pub contract_address: Felt,
+    pub work: u64,
+    workload: PhantomData<W>,
+}
+
+impl<W: Workload> Shooter for WorkloadShooter<W> {
+    const NAME: &'static str = W::NAME;
+
+    fn validate(config: &GatlingConfig, shooter: &Shooters) -> color_eyre::Result<()> {
+        // The entrypoints of the workload contract take the amount of work as an u32
+        if let Some(work) = shooter.work {
+            ensure!(
+                u32::try_from(work).is_ok(),
+                "Work {work} does not fit in the u32 taken by the workload contract"
+            );
+        }
+
+        workload_contract(config, shooter).map(drop)
+    }
+
+    async fn setup(setup: &mut GatlingSetup, config: &Shooters) -> color_eyre::Result<Self> {
+        let contract = workload_contract(setup.config(), config)?.clone();
+
+        let class_hash = setup.declare_contract(&contract).await?;
+        let contract_address = deploy_workload_contract(setup, class_hash).await?;
+
+        Ok(Self {
+            contract_address,
+            work: config.work.unwrap_or(DEFAULT_WORK),
+            workload: PhantomData,
+        })
+    }
+
+    fn execute(
+        self: Arc<Self>,
+        calls_per_tx: CallsPerTx,
+        batch: Option<Arc<Batch>>,
+    ) -> TransactionFunction
+    where
+        Self: Send + Sync + 'static,
+    {
+        execute_invoke(self, calls_per_tx, batch)
+    }
+
+    fn artifacts(&self, artifacts: &mut Artifacts) {
+        artifacts.add_contract("workload", self.contract_address);
+    }
+}
+
+impl<W: Workload> InvokeShooter for WorkloadShooter<W> {
+    fn get_execution_data(&self, _account: &StarknetAccount) -> Call {
+        Call {
+            to: self.contract_address,
+            selector: W::SELECTOR,
+            calldata: vec![Felt::from(self.work)],
+        }
+    }
+}
+
+/// Contract of the shooter, falling back to the workload contract shared by all the workload shooters
+fn workload_contract<'a>(
+    config: &'a GatlingConfig,
+    shooter: &'a Shooters,
+) -> color_eyre::Result<&'a ContractSourceConfig> {
+    shooter
+        .contract
+        .as_ref()
+        .or(config.setup.workload_contract.as_ref())
+        .ok_or_eyre("No workload contract configured, set `setup.workload_contract`")
+}
+
+/// Deploy the workload contract shared by all the workload shooters
+pub async fn deploy_workload_contract(
+    setup: &GatlingSetup,
+    class_hash: Felt,
+) -> color_eyre::Result<Felt> {
+    let deployer = setup.deployer_account();
+    let salt = setup.config().deployer.salt;
+
+    let address = get_udc_deployed_address(salt, class_hash, &UdcUniqueness::NotUnique, &[]);
+
+    if let Ok(contract_class_hash) = setup
+        .rpc_client()
+        .get_class_hash_at(BlockId::Tag(BlockTag::Pending), address)
+        .await
+    {
+        if contract_class_hash == class_hash {
+            tracing::warn!("Workload contract already deployed at address {address:#064x}");
+            return Ok(address);
+        } else {
+            bail!("Workload contract {address:#064x} already deployed with a different class hash {contract_class_hash:#064x}, expected {class_hash:#064x}");
+        }
+    }
+
+    let contract_factory = ContractFactory::new(class_hash, deployer.clone());
+    let nonce = deployer.get_nonce().await?;
+
+    tracing::info!(
+        "Deploying workload contract with nonce={}, address={:#064x}",
+        nonce,
+        address
+    );
+
+    let result = contract_factory
+        .deploy_v1(vec![], salt, false)
+        .nonce(nonce)
+        .max_fee(MAX_FEE)
+        .send()
+        .await?;
+    wait_for_tx(setup.rpc_client(), result.transaction_hash, CHECK_INTERVAL).await?;
+
+    tracing::info!(
+        "Workload contract deployed at address {:#064x} by {:#064x}",
+        address,
+        deployer.address()
+    );
+
+    Ok(address)
+}
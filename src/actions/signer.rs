@@ -0,0 +1,247 @@
+use std::{fmt, path::Path, process::Stdio, sync::Arc};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, WrapErr};
+use serde_json::{json, Value};
+use starknet::{
+    core::{crypto::Signature, types::Felt},
+    signers::{LocalWallet, Signer, SignerInteractivityContext, SigningKey, VerifyingKey},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::Mutex,
+};
+
+use crate::config::{DeployerConfig, ExternalSignerConfig, KeystoreConfig};
+
+/// Signer of the deployer, which also signs for the accounts of the goose users
+#[derive(Debug, Clone)]
+pub enum GatlingSigner {
+    Local(LocalWallet),
+    External(Arc<ExternalSigner>),
+}
+
+impl GatlingSigner {
+    /// Build the signer from the one key source configured for the deployer
+    pub async fn from_config(config: &DeployerConfig) -> color_eyre::Result<Self> {
+        config.validate()?;
+
+        let local = |key| {
+            Ok(Self::Local(LocalWallet::from(
+                SigningKey::from_secret_scalar(key),
+            )))
+        };
+
+        match (
+            config.signing_key,
+            &config.key_file,
+            &config.keystore,
+            &config.external_signer,
+        ) {
+            (Some(signing_key), None, None, None) => local(signing_key),
+            (None, Some(key_file), None, None) => local(read_key_file(key_file)?),
+            (None, None, Some(keystore), None) => {
+                Ok(Self::Local(LocalWallet::from(open_keystore(keystore)?)))
+            }
+            (None, None, None, Some(external)) => {
+                Ok(Self::External(Arc::new(ExternalSigner::spawn(external)?)))
+            }
+            _ => unreachable!("The deployer was validated to have exactly one key source"),
+        }
+    }
+}
+
+fn read_key_file(path: &Path) -> color_eyre::Result<Felt> {
+    let key = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Could not read key file {}", path.display()))?;
+
+    Felt::from_hex(key.trim()).map_err(|_| {
+        eyre!(
+            "Key file {} does not hold a hex private key",
+            path.display()
+        )
+    })
+}
+
+fn open_keystore(config: &KeystoreConfig) -> color_eyre::Result<SigningKey> {
+    let password = match &config.password_file {
+        Some(path) => std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Could not read password file {}", path.display()))?
+            .trim_end_matches(['\n', '\r'])
+            .to_owned(),
+        None => {
+            rpassword::prompt_password(format!("Password of keystore {}: ", config.path.display()))?
+        }
+    };
+
+    SigningKey::from_keystore(&config.path, &password).map_err(|err| {
+        eyre!(
+            "Could not decrypt keystore {}: {err}",
+            config.path.display()
+        )
+    })
+}
+
+#[async_trait]
+impl Signer for GatlingSigner {
+    type GetPublicKeyError = SignerError;
+    type SignError = SignerError;
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, Self::GetPublicKeyError> {
+        match self {
+            Self::Local(wallet) => Ok(wallet
+                .get_public_key()
+                .await
+                .expect("Local wallet public key is infallible")),
+            Self::External(signer) => signer.get_public_key().await,
+        }
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, Self::SignError> {
+        match self {
+            Self::Local(wallet) => wallet
+                .sign_hash(hash)
+                .await
+                .map_err(|err| SignerError(err.to_string())),
+            Self::External(signer) => signer.sign_hash(hash).await,
+        }
+    }
+
+    fn is_interactive(&self, _context: SignerInteractivityContext<'_>) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct SignerError(String);
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signer failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+/// Signer holding the key outside of gatling, which is sent one JSON request per signature:
+/// `{"method": "get_public_key"}` answered with `{"public_key": "0x.."}`, and
+/// `{"method": "sign_hash", "hash": "0x.."}` answered with `{"signature": ["0x<r>", "0x<s>"]}`.
+/// Failures are answered with `{"error": "<message>"}`
+#[derive(Debug)]
+pub enum ExternalSigner {
+    /// Requests are the bodies of POST requests to `url`
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+    /// Requests and responses are lines on the standard input and output of a child process
+    Stdio {
+        io: Mutex<(ChildStdin, BufReader<ChildStdout>)>,
+        _child: Child,
+    },
+}
+
+impl ExternalSigner {
+    fn spawn(config: &ExternalSignerConfig) -> color_eyre::Result<Self> {
+        match config {
+            ExternalSignerConfig::Http { url } => Ok(Self::Http {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+            }),
+            ExternalSignerConfig::Stdio { command, args } => {
+                let mut child = Command::new(command)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .wrap_err_with(|| format!("Could not start external signer `{command}`"))?;
+
+                let stdin = child.stdin.take().expect("Stdin is piped");
+                let stdout = child.stdout.take().expect("Stdout is piped");
+
+                Ok(Self::Stdio {
+                    io: Mutex::new((stdin, BufReader::new(stdout))),
+                    _child: child,
+                })
+            }
+        }
+    }
+
+    async fn get_public_key(&self) -> Result<VerifyingKey, SignerError> {
+        let response = self.request(json!({ "method": "get_public_key" })).await?;
+
+        let public_key = felt_field(&response["public_key"])?;
+
+        Ok(VerifyingKey::from_scalar(public_key))
+    }
+
+    async fn sign_hash(&self, hash: &Felt) -> Result<Signature, SignerError> {
+        let response = self
+            .request(json!({ "method": "sign_hash", "hash": format!("{hash:#x}") }))
+            .await?;
+
+        match response["signature"].as_array().map(Vec::as_slice) {
+            Some([r, s]) => Ok(Signature {
+                r: felt_field(r)?,
+                s: felt_field(s)?,
+            }),
+            _ => Err(SignerError(format!("Unexpected signature in {response}"))),
+        }
+    }
+
+    async fn request(&self, request: Value) -> Result<Value, SignerError> {
+        let error = |err: &dyn fmt::Display| SignerError(err.to_string());
+
+        let response = match self {
+            Self::Http { client, url } => client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .body(request.to_string())
+                .send()
+                .await
+                .map_err(|err| error(&err))?
+                .text()
+                .await
+                .map_err(|err| error(&err))?,
+            Self::Stdio { io, .. } => {
+                // One request at a time so that responses are read in order
+                let mut io = io.lock().await;
+                let (stdin, stdout) = &mut *io;
+
+                stdin
+                    .write_all(format!("{request}\n").as_bytes())
+                    .await
+                    .map_err(|err| error(&err))?;
+                stdin.flush().await.map_err(|err| error(&err))?;
+
+                let mut line = String::new();
+                if stdout
+                    .read_line(&mut line)
+                    .await
+                    .map_err(|err| error(&err))?
+                    == 0
+                {
+                    return Err(SignerError("External signer exited".into()));
+                }
+
+                line
+            }
+        };
+
+        let response: Value = serde_json::from_str(&response).map_err(|err| error(&err))?;
+
+        match response.get("error") {
+            Some(message) => Err(SignerError(message.to_string())),
+            None => Ok(response),
+        }
+    }
+}
+
+fn felt_field(value: &Value) -> Result<Felt, SignerError> {
+    value
+        .as_str()
+        .and_then(|felt| Felt::from_hex(felt).ok())
+        .ok_or_else(|| SignerError(format!("Expected a hex felt, got {value}")))
+}
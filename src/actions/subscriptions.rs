@@ -0,0 +1,311 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use color_eyre::eyre::{bail, eyre, OptionExt};
+use serde_json::Value;
+use starknet::{
+    core::types::{BlockId, MaybePendingBlockWithTxHashes},
+    providers::{jsonrpc::JsonRpcResponse, Provider},
+};
+use tokio::{task::JoinSet, time::timeout_at};
+
+use crate::{
+    config::{SubscriptionBenchConfig, SubscriptionMethod},
+    metrics::{BenchmarkReport, MetricResult},
+};
+
+use super::{
+    setup::{is_compatible, GatlingSetup},
+    ws::WsClient,
+};
+
+/// RPC spec version WebSocket subscriptions were introduced in, which `rpc.ws_url` must serve
+const SUBSCRIPTION_SPEC_VERSION: &str = "0.8";
+
+/// Notification sent instead of the subscribed one when the chain reorganizes
+const REORG_NOTIFICATION: &str = "starknet_subscriptionReorg";
+
+/// What a single subscriber went through
+#[derive(Debug, Default)]
+struct Subscriber {
+    subscribed: bool,
+    subscribe_time: u64,
+    /// Whether the connection broke before the end of the benchmark
+    dropped: bool,
+    notifications: Vec<Received>,
+    reorgs: u64,
+}
+
+/// A notification along with the block it is about, if any
+#[derive(Debug)]
+struct Received {
+    block: Option<NotifiedBlock>,
+    /// Unix time the notification was received at, in milliseconds
+    at: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NotifiedBlock {
+    /// New heads notifications carry the block timestamp, in seconds
+    Timestamp(u64),
+    /// Events only carry the block number
+    Number(u64),
+}
+
+/// Make sure `url` serves a spec version with subscriptions, as the spec version
+/// of `rpc.url` only tells about the HTTP endpoint of the node
+pub async fn check_spec_version(url: &str) -> color_eyre::Result<()> {
+    let mut client = WsClient::connect(url).await?;
+
+    let version = match client
+        .request("starknet_specVersion", &Value::Array(Vec::new()))
+        .await?
+    {
+        JsonRpcResponse::Success { result, .. } => result,
+        JsonRpcResponse::Error { error, .. } => {
+            bail!("Failed to get the RPC spec version of {url}: {error}")
+        }
+    };
+
+    if let Err(error) = client.close().await {
+        tracing::warn!("Failed to close spec version connection: {error}");
+    }
+
+    match version.as_str() {
+        Some(version) if is_compatible(version, SUBSCRIPTION_SPEC_VERSION) => Ok(()),
+        _ => bail!(
+            "{url} serves RPC spec {version} but subscriptions need {SUBSCRIPTION_SPEC_VERSION}"
+        ),
+    }
+}
+
+/// Open `subscribers` concurrent subscriptions, each on its own connection,
+/// and listen to their notifications for the configured duration
+pub async fn subscription_bench(
+    setup: &GatlingSetup,
+    bench: &SubscriptionBenchConfig,
+) -> color_eyre::Result<BenchmarkReport> {
+    let url = setup
+        .config()
+        .rpc
+        .ws_url
+        .clone()
+        .ok_or_eyre("Subscription benches need `rpc.ws_url`")?;
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(bench.duration);
+    let mut joinset = JoinSet::new();
+
+    tracing::info!(
+        "Opening {} `{}` subscriptions for {}s",
+        bench.subscribers,
+        bench.method.method(),
+        bench.duration
+    );
+
+    for _ in 0..bench.subscribers {
+        let url = url.clone();
+        let method = bench.method;
+        let params = Value::Object(bench.params.clone());
+
+        joinset.spawn(async move { subscribe(&url, method, &params, deadline).await });
+    }
+
+    let mut subscribers = Vec::new();
+
+    while let Some(result) = joinset.join_next().await {
+        subscribers.push(result.map_err(|err| eyre!(err))?);
+    }
+
+    let notified: u64 = subscribers
+        .iter()
+        .map(|s| s.notifications.len() as u64)
+        .sum();
+
+    let mut report = BenchmarkReport::new(bench.name.clone(), notified as usize);
+
+    let subscribed: Vec<_> = subscribers.iter().filter(|s| s.subscribed).collect();
+    let dropped = subscribed.iter().filter(|s| s.dropped).count() as u64;
+
+    report.metrics.extend_from_slice(&[
+        MetricResult {
+            name: "Subscribers".into(),
+            unit: "subscribers",
+            value: (subscribed.len() as u64).into(),
+        },
+        MetricResult {
+            name: "Failed Subscribers".into(),
+            unit: "subscribers",
+            value: (subscribers.len() - subscribed.len()).into(),
+        },
+        MetricResult {
+            name: "Dropped Subscribers".into(),
+            unit: "subscribers",
+            value: dropped.into(),
+        },
+        MetricResult {
+            name: "Notifications".into(),
+            unit: "notifications",
+            value: notified.into(),
+        },
+        MetricResult {
+            name: "Reorg Notifications".into(),
+            unit: "notifications",
+            value: subscribers.iter().map(|s| s.reorgs).sum::<u64>().into(),
+        },
+    ]);
+
+    if !subscribed.is_empty() {
+        report.metrics.push(MetricResult {
+            name: "Average Notifications per Subscriber".into(),
+            unit: "notifications",
+            value: (notified as f64 / subscribed.len() as f64).into(),
+        });
+    }
+
+    let mut subscribe_times: Vec<_> = subscribed.iter().map(|s| s.subscribe_time).collect();
+    report.with_latencies(&mut subscribe_times, 0, Some("Subscribe"));
+
+    let timestamps = block_timestamps(setup, &subscribers).await?;
+
+    // Block timestamps have a resolution of a second, so are the delays
+    let mut delays: Vec<_> = subscribers
+        .iter()
+        .flat_map(|s| &s.notifications)
+        .filter_map(|received| {
+            let timestamp = match received.block? {
+                NotifiedBlock::Timestamp(timestamp) => timestamp,
+                NotifiedBlock::Number(number) => *timestamps.get(&number)?,
+            };
+
+            Some(received.at.saturating_sub(timestamp * 1000))
+        })
+        .collect();
+
+    report.with_latencies(&mut delays, 0, Some("Notification Delay"));
+
+    Ok(report)
+}
+
+async fn subscribe(
+    url: &str,
+    method: SubscriptionMethod,
+    params: &Value,
+    deadline: tokio::time::Instant,
+) -> Subscriber {
+    let mut subscriber = Subscriber::default();
+
+    let start = Instant::now();
+
+    let mut client = match WsClient::connect(url).await {
+        Ok(client) => client,
+        Err(error) => {
+            tracing::error!("Failed to connect subscriber: {error}");
+            return subscriber;
+        }
+    };
+
+    let subscription_id = match client.request(method.method(), params).await {
+        Ok(JsonRpcResponse::Success { result, .. }) => result,
+        Ok(JsonRpcResponse::Error { error, .. }) => {
+            tracing::error!("Subscription was rejected: {error}");
+            return subscriber;
+        }
+        Err(error) => {
+            tracing::error!("Failed to subscribe: {error}");
+            return subscriber;
+        }
+    };
+
+    subscriber.subscribed = true;
+    subscriber.subscribe_time = start.elapsed().as_millis() as u64;
+
+    loop {
+        let notification = match timeout_at(deadline, client.notification()).await {
+            Err(_) => break,
+            Ok(Ok(notification)) => notification,
+            Ok(Err(error)) => {
+                tracing::error!("Subscriber dropped: {error}");
+                subscriber.dropped = true;
+                return subscriber;
+            }
+        };
+
+        if notification.params.subscription_id != subscription_id {
+            continue;
+        }
+
+        if notification.method == REORG_NOTIFICATION {
+            subscriber.reorgs += 1;
+            continue;
+        }
+
+        let result = &notification.params.result;
+        let block = match method {
+            SubscriptionMethod::NewHeads => {
+                result["timestamp"].as_u64().map(NotifiedBlock::Timestamp)
+            }
+            SubscriptionMethod::Events => {
+                result["block_number"].as_u64().map(NotifiedBlock::Number)
+            }
+            SubscriptionMethod::PendingTransactions | SubscriptionMethod::TransactionStatus => None,
+        };
+
+        subscriber.notifications.push(Received {
+            block,
+            at: unix_millis(),
+        });
+    }
+
+    let unsubscribe = Value::Array(vec![subscription_id]);
+    if let Err(error) = client.request("starknet_unsubscribe", &unsubscribe).await {
+        tracing::warn!("Failed to unsubscribe: {error}");
+    }
+
+    if let Err(error) = client.close().await {
+        tracing::warn!("Failed to close subscriber connection: {error}");
+    }
+
+    subscriber
+}
+
+/// Timestamps of the blocks notifications only know the number of,
+/// fetched once listening is over to not disturb the measurements
+async fn block_timestamps(
+    setup: &GatlingSetup,
+    subscribers: &[Subscriber],
+) -> color_eyre::Result<BTreeMap<u64, u64>> {
+    let mut timestamps = BTreeMap::new();
+
+    let numbers = subscribers
+        .iter()
+        .flat_map(|s| &s.notifications)
+        .filter_map(|received| match received.block {
+            Some(NotifiedBlock::Number(number)) => Some(number),
+            _ => None,
+        });
+
+    for number in numbers {
+        if timestamps.contains_key(&number) {
+            continue;
+        }
+
+        if let MaybePendingBlockWithTxHashes::Block(block) = setup
+            .rpc_client()
+            .get_block_with_tx_hashes(BlockId::Number(number))
+            .await?
+        {
+            timestamps.insert(number, block.timestamp);
+        }
+    }
+
+    Ok(timestamps)
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
@@ -0,0 +1,158 @@
+use color_eyre::eyre::eyre;
+use futures::{stream, StreamExt};
+use starknet::{
+    accounts::{Account, ConnectedAccount},
+    core::types::{BlockId, BlockTag, Call, Felt, StarknetError},
+    macros::selector,
+    providers::{Provider, ProviderError},
+};
+
+use crate::{
+    metrics::{BenchmarkReport, MetricResult},
+    utils::{u256_limbs, wait_for_tx},
+};
+
+use super::setup::{GatlingSetup, StarknetAccount, CHECK_INTERVAL};
+
+/// How many accounts are swept at once, each one sends a single transaction
+const SWEEPS_IN_FLIGHT: usize = 16;
+
+/// The max fee of a sweep is its estimated fee times this margin,
+/// what the transaction does not spend of it stays on the account
+const FEE_MARGIN: u64 = 2;
+
+/// What sweeping an account left behind
+enum Sweep {
+    Recovered(Felt),
+    /// Holding less than the fee of a transfer
+    Dust(Felt),
+    NotDeployed,
+}
+
+/// Transfer what is left of the fee tokens of the goose user accounts back to the deployer
+pub async fn sweep_accounts(setup: &GatlingSetup) -> color_eyre::Result<BenchmarkReport> {
+    let accounts = setup.derived_accounts().await?;
+
+    tracing::info!("Sweeping {} accounts", accounts.len());
+
+    let sweeps: Vec<_> = stream::iter(&accounts)
+        .map(|account| sweep(setup, account))
+        .buffer_unordered(SWEEPS_IN_FLIGHT)
+        .collect()
+        .await;
+
+    let (mut swept, mut recovered) = (0u64, Felt::ZERO);
+    let (mut dust_accounts, mut dust) = (0u64, Felt::ZERO);
+
+    for sweep in sweeps {
+        match sweep? {
+            Sweep::Recovered(amount) => {
+                swept += 1;
+                recovered += amount;
+            }
+            Sweep::Dust(amount) => {
+                dust_accounts += 1;
+                dust += amount;
+            }
+            Sweep::NotDeployed => {}
+        }
+    }
+
+    tracing::info!("Recovered {recovered} fee tokens from {swept} accounts");
+
+    let deployer_balance = setup
+        .fee_token_balance(setup.deployer_account().address())
+        .await?;
+
+    let mut report = BenchmarkReport::new("Teardown".into(), swept as usize);
+
+    report.metrics.extend_from_slice(&[
+        MetricResult {
+            name: "Swept Accounts".into(),
+            unit: "accounts",
+            value: swept.into(),
+        },
+        MetricResult {
+            name: "Recovered".into(),
+            unit: "fee tokens",
+            value: recovered.to_string().into(),
+        },
+        MetricResult {
+            name: "Dust Accounts".into(),
+            unit: "accounts",
+            value: dust_accounts.into(),
+        },
+        MetricResult {
+            name: "Dust".into(),
+            unit: "fee tokens",
+            value: dust.to_string().into(),
+        },
+        MetricResult {
+            name: "Deployer Balance".into(),
+            unit: "fee tokens",
+            value: deployer_balance.to_string().into(),
+        },
+    ]);
+
+    Ok(report)
+}
+
+/// Transfer the fee token balance of `account` minus the fee of the transfer to the deployer
+async fn sweep(setup: &GatlingSetup, account: &StarknetAccount) -> color_eyre::Result<Sweep> {
+    let address = account.address();
+
+    match setup
+        .rpc_client()
+        .get_class_hash_at(BlockId::Tag(BlockTag::Pending), address)
+        .await
+    {
+        Ok(_) => {}
+        Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => {
+            return Ok(Sweep::NotDeployed)
+        }
+        Err(err) => return Err(eyre!(err)),
+    }
+
+    let balance = setup.fee_token_balance(address).await?;
+    if balance == Felt::ZERO {
+        return Ok(Sweep::Dust(balance));
+    }
+
+    let nonce = account.get_nonce().await?;
+    let transfer = |amount: Felt| {
+        let (amount_low, amount_high) = u256_limbs(amount);
+
+        vec![Call {
+            to: setup.config().setup.fee_token_address,
+            selector: selector!("transfer"),
+            calldata: vec![setup.deployer_account().address(), amount_low, amount_high],
+        }]
+    };
+
+    let estimate = account
+        .execute_v1(transfer(balance))
+        .nonce(nonce)
+        .estimate_fee()
+        .await?;
+    let max_fee = estimate.overall_fee * Felt::from(FEE_MARGIN);
+
+    if balance <= max_fee {
+        tracing::info!("Account {address:#064x} only holds {balance} fee tokens, leaving them");
+        return Ok(Sweep::Dust(balance));
+    }
+
+    let amount = balance - max_fee;
+
+    tracing::info!("Sweeping {amount} fee tokens from account {address:#064x} with nonce={nonce}");
+
+    let result = account
+        .execute_v1(transfer(amount))
+        .max_fee(max_fee)
+        .nonce(nonce)
+        .send()
+        .await?;
+
+    wait_for_tx(setup.rpc_client(), result.transaction_hash, CHECK_INTERVAL).await?;
+
+    Ok(Sweep::Recovered(amount))
+}
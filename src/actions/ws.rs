@@ -0,0 +1,204 @@
+use std::{collections::VecDeque, time::Instant};
+
+use color_eyre::eyre::{bail, ensure, eyre, OptionExt};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use starknet::providers::jsonrpc::{JsonRpcMethod, JsonRpcResponse};
+use tokio::{net::TcpStream, task::JoinSet};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{config::GatlingConfig, generators::parameters::ReadParameters, spec};
+
+use super::goose::positional_params;
+
+/// A JSON-RPC client over a single WebSocket connection
+pub struct WsClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+    /// Notifications received while waiting for a response
+    notifications: VecDeque<Notification>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Notification {
+    pub method: String,
+    pub params: NotificationParams,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationParams {
+    pub subscription_id: Value,
+    pub result: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum WsMessage {
+    Response(JsonRpcResponse<Value>),
+    Notification(Notification),
+}
+
+#[derive(Debug, Serialize)]
+struct WsRequest<'a, M> {
+    id: u64,
+    jsonrpc: &'static str,
+    method: M,
+    params: &'a Value,
+}
+
+impl WsClient {
+    pub async fn connect(url: &str) -> color_eyre::Result<Self> {
+        let (stream, _) = connect_async(url).await?;
+
+        Ok(Self {
+            stream,
+            next_id: 0,
+            notifications: VecDeque::new(),
+        })
+    }
+
+    /// Send a request and wait for its response, keeping any notification received meanwhile
+    pub async fn request(
+        &mut self,
+        method: impl Serialize,
+        params: &Value,
+    ) -> color_eyre::Result<JsonRpcResponse<Value>> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = WsRequest {
+            id,
+            jsonrpc: "2.0",
+            method,
+            params,
+        };
+
+        self.stream
+            .send(Message::Text(serde_json::to_string(&request)?))
+            .await?;
+
+        loop {
+            match self.next_message().await? {
+                WsMessage::Response(response) if response_id(&response) == id => {
+                    return Ok(response)
+                }
+                WsMessage::Response(_) => continue,
+                WsMessage::Notification(notification) => self.notifications.push_back(notification),
+            }
+        }
+    }
+
+    /// Wait for the next subscription notification
+    pub async fn notification(&mut self) -> color_eyre::Result<Notification> {
+        if let Some(notification) = self.notifications.pop_front() {
+            return Ok(notification);
+        }
+
+        loop {
+            if let WsMessage::Notification(notification) = self.next_message().await? {
+                return Ok(notification);
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> color_eyre::Result<()> {
+        Ok(self.stream.close(None).await?)
+    }
+
+    async fn next_message(&mut self) -> color_eyre::Result<WsMessage> {
+        loop {
+            let message = self
+                .stream
+                .next()
+                .await
+                .ok_or_eyre("WebSocket connection was closed")??;
+
+            match message {
+                Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                Message::Ping(payload) => self.stream.send(Message::Pong(payload)).await?,
+                Message::Close(frame) => bail!("WebSocket connection was closed: {frame:?}"),
+                _ => continue,
+            }
+        }
+    }
+}
+
+fn response_id<T>(response: &JsonRpcResponse<T>) -> u64 {
+    match response {
+        JsonRpcResponse::Success { id, .. } | JsonRpcResponse::Error { id, .. } => *id,
+    }
+}
+
+/// Response times of requests sent over WebSocket
+#[derive(Debug, Default)]
+pub struct Latencies {
+    pub times: Vec<u64>,
+    pub failed: u64,
+}
+
+/// Read benchmark over WebSocket, each concurrent user has its own connection
+pub async fn read_method(
+    config: &GatlingConfig,
+    amount: u64,
+    method: JsonRpcMethod,
+    parameters: ReadParameters,
+) -> color_eyre::Result<Latencies> {
+    let url = config
+        .rpc
+        .ws_url
+        .clone()
+        .ok_or_eyre("WebSocket read benches need `rpc.ws_url`")?;
+
+    let users = config.run.concurrency;
+    ensure!(
+        amount >= users,
+        "Too few read calls for the amount of concurrent users"
+    );
+    let iterations = amount / users;
+
+    let mut joinset = JoinSet::new();
+
+    for _ in 0..users {
+        let (url, parameters) = (url.clone(), parameters.clone());
+
+        joinset.spawn(async move {
+            let mut client = WsClient::connect(&url).await?;
+            let mut latencies = Latencies::default();
+
+            for _ in 0..iterations {
+                let params = positional_params(parameters.sample());
+
+                let start = Instant::now();
+                let response = client.request(method, &params).await?;
+                let time = start.elapsed().as_millis() as u64;
+
+                match response {
+                    JsonRpcResponse::Success { result, .. } => {
+                        latencies.times.push(time);
+                        spec::validate_response(method, &json!({ "result": result }));
+                    }
+                    JsonRpcResponse::Error { error, .. } => {
+                        latencies.failed += 1;
+                        let error = json!({ "code": error.code, "message": error.message });
+                        spec::validate_response(method, &json!({ "error": error }));
+                    }
+                }
+            }
+
+            client.close().await?;
+
+            color_eyre::Result::<_>::Ok(latencies)
+        });
+    }
+
+    let mut latencies = Latencies::default();
+
+    while let Some(result) = joinset.join_next().await {
+        let user_latencies = result.map_err(|err| eyre!(err))??;
+        latencies.times.extend(user_latencies.times);
+        latencies.failed += user_latencies.failed;
+    }
+
+    Ok(latencies)
+}
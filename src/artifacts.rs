@@ -0,0 +1,52 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
+
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+/// What the write shooters left on chain, saved by `shoot` so that
+/// read benchmarks can query realistic data without a parameters file
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Artifacts {
+    /// Hashes of all the transactions sent and verified by the shooters
+    pub transactions: Vec<Felt>,
+    /// Addresses of the accounts sending the transactions
+    pub accounts: Vec<Felt>,
+    /// Addresses of the contracts deployed by the shooters, by kind such as `erc20`
+    pub contracts: BTreeMap<String, Vec<Felt>>,
+}
+
+impl Artifacts {
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let file = File::open(path)
+            .wrap_err_with(|| format!("Could not open artifacts {}", path.display()))?;
+
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("Could not create directory {}", parent.display()))?;
+        }
+
+        let file = File::create(path)
+            .wrap_err_with(|| format!("Could not create artifacts {}", path.display()))?;
+
+        Ok(serde_json::to_writer_pretty(file, self)?)
+    }
+
+    /// Record a deployed contract of the given kind, once
+    pub fn add_contract(&mut self, kind: &str, address: Felt) {
+        let addresses = self.contracts.entry(kind.to_string()).or_default();
+
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+}
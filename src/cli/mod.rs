@@ -3,6 +3,8 @@
 // Imports
 use clap::{Args, Parser, Subcommand};
 
+use crate::config::ConfigOverrides;
+
 const VERSION_STRING: &str = env!("CARGO_PKG_VERSION");
 
 /// Main CLI struct
@@ -29,6 +31,18 @@ pub enum Command {
     Shoot {},
     // Trigger a read load test
     Read {},
+    /// Trigger a write load test with the read load test running concurrently.
+    ShootAndRead {},
+    /// Trigger a WebSocket subscriptions load test.
+    Subscribe {},
+    /// Compare the read responses of the node against a reference node.
+    DiffRead {},
+    /// Send the fee tokens left on the load test accounts back to the deployer.
+    Teardown {},
+    /// Validate the configuration and show the planned load without sending anything.
+    Check {},
+    /// List the available shooters and the benches defined in the configuration.
+    List {},
 }
 
 #[derive(Debug, Args)]
@@ -36,4 +50,76 @@ pub struct GlobalOpts {
     /// Configuration file path, optional.
     #[clap(short, long, global = true)]
     pub config_path: Option<String>,
+
+    /// Allow sending transactions to public chains such as mainnet or sepolia.
+    #[clap(long = "i-know-what-im-doing", global = true)]
+    pub i_know_what_im_doing: bool,
+
+    /// Override a configuration value, such as `run.concurrency=50`, can be repeated.
+    #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value, global = true)]
+    pub set: Vec<(String, String)>,
+
+    /// Starknet RPC url, overrides `rpc.url`.
+    #[clap(long, global = true)]
+    pub rpc_url: Option<String>,
+
+    /// Amount of concurrent users, overrides `run.concurrency`.
+    #[clap(long, global = true)]
+    pub concurrency: Option<u64>,
+
+    /// Amount of transactions of a shooter, such as `transfer=1000`, can be repeated.
+    /// Shooters missing from the configuration are added.
+    #[clap(long = "shooter", value_name = "NAME=AMOUNT", value_parser = parse_shooter, global = true)]
+    pub shooters: Vec<(String, u64)>,
+
+    /// Path of the report, overrides `report.output_location`.
+    #[clap(long, global = true)]
+    pub output: Option<String>,
+
+    /// Only run the shooters and benches with these names or glob patterns, can be repeated.
+    #[clap(long, global = true)]
+    pub only: Vec<String>,
+
+    /// Do not run the shooters and benches with these names or glob patterns, can be repeated.
+    #[clap(long, global = true)]
+    pub skip: Vec<String>,
+}
+
+impl GlobalOpts {
+    /// Configuration values set by the options, the dedicated options win over `--set`
+    pub fn overrides(&self) -> ConfigOverrides {
+        let mut values = self.set.clone();
+
+        let options = [
+            ("rpc.url", self.rpc_url.clone()),
+            ("run.concurrency", self.concurrency.map(|c| c.to_string())),
+            ("report.output_location", self.output.clone()),
+        ];
+
+        values.extend(
+            options
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.to_owned(), value?))),
+        );
+
+        ConfigOverrides {
+            values,
+            shooters: self.shooters.clone(),
+        }
+    }
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{arg}`"))
+}
+
+fn parse_shooter(arg: &str) -> Result<(String, u64), String> {
+    let (name, amount) = parse_key_value(arg)?;
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("invalid amount of transactions `{amount}`: {e}"))?;
+
+    Ok((name, amount))
 }
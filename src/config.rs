@@ -1,23 +1,30 @@
 //! General configuration
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
 
-use color_eyre::eyre::Result;
-use config::{builder::DefaultState, Config, ConfigBuilder};
+use color_eyre::eyre::{ensure, Result};
+use config::Config;
+use rand::Rng;
 
 use serde::Deserialize;
 use serde::{de::Error as DeError, Deserializer};
 use serde_json::{Map, Value};
 use starknet::{
     core::{
-        types::{contract::CompiledClass, Felt},
+        types::{
+            contract::{legacy::LegacyContractClass, CompiledClass, SierraClass},
+            Felt,
+        },
         utils::{cairo_short_string_to_felt, CairoShortStringToFeltError},
     },
     providers::jsonrpc::JsonRpcMethod,
 };
 
+use crate::utils::glob_match;
+
 /// Configuration for the application.
 #[derive(Debug, Deserialize, Clone)]
 pub struct GatlingConfig {
@@ -37,12 +44,59 @@ pub struct GatlingConfig {
 #[allow(unused)]
 pub struct RpcConfig {
     pub url: String,
+    /// WebSocket endpoint used by websocket read benches and subscriptions
+    pub ws_url: Option<String>,
+    /// Endpoints the submitted transactions and read requests are spread over, defaults to `url`
+    #[serde(default)]
+    pub endpoints: Vec<EndpointConfig>,
+    #[serde(default)]
+    pub strategy: EndpointStrategy,
+    /// Endpoint transactions are verified on and block metrics are gathered from, defaults to `url`
+    pub verification_url: Option<String>,
+    /// Node the responses of `url` are compared against by `diff-read`
+    pub reference_url: Option<String>,
+}
+
+impl RpcConfig {
+    pub fn verification_url(&self) -> &str {
+        self.verification_url.as_deref().unwrap_or(&self.url)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EndpointConfig {
+    pub url: String,
+    /// Share of the load sent to this endpoint with the `weighted` strategy
+    #[serde(default = "default_endpoint_weight")]
+    pub weight: u64,
+}
+
+fn default_endpoint_weight() -> u64 {
+    1
+}
+
+/// How the load is spread over the endpoints
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointStrategy {
+    /// Each request goes to the next endpoint
+    #[default]
+    RoundRobin,
+    /// Each goose user sends all its requests to the same endpoint
+    Pinned,
+    /// Each request goes to an endpoint drawn according to the endpoint weights
+    Weighted,
 }
 
 impl Default for RpcConfig {
     fn default() -> Self {
         Self {
             url: "http://localhost:9944".to_string(),
+            ws_url: None,
+            endpoints: Vec::new(),
+            strategy: EndpointStrategy::default(),
+            verification_url: None,
+            reference_url: None,
         }
     }
 }
@@ -77,6 +131,22 @@ impl ContractSourceConfig {
         }
     }
 
+    /// Class hash of the contract, computed from its artifact
+    pub fn get_class_hash(&self) -> Result<Felt> {
+        let file = BufReader::new(File::open(self.get_contract_path())?);
+
+        match self {
+            ContractSourceConfig::V0(_) => {
+                let contract_class: LegacyContractClass = serde_json::from_reader(file)?;
+                Ok(contract_class.class_hash()?)
+            }
+            ContractSourceConfig::V1(_) => {
+                let contract_class: SierraClass = serde_json::from_reader(file)?;
+                Ok(contract_class.class_hash()?)
+            }
+        }
+    }
+
     pub fn get_casm_hash(&self) -> Result<Option<Felt>> {
         if let ContractSourceConfig::V1(config) = self {
             let mut casm_file = std::fs::File::open(&config.casm_path)?;
@@ -94,30 +164,207 @@ pub struct SetupConfig {
     pub erc20_contract: ContractSourceConfig,
     pub erc721_contract: ContractSourceConfig,
     pub account_contract: ContractSourceConfig,
+    /// Synthetic workload contract built from `contracts/workloads`
+    pub workload_contract: Option<ContractSourceConfig>,
     pub fee_token_address: Felt,
-    #[serde(deserialize_with = "from_str_deserializer")]
-    pub chain_id: Felt,
+    /// Detected from the node when not set, the run is refused if they differ
+    #[serde(default, deserialize_with = "optional_from_str_deserializer")]
+    pub chain_id: Option<Felt>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeployerConfig {
     pub salt: Felt,
     pub address: Felt,
-    pub signing_key: Felt,
+    /// Private key in plaintext, exactly one of the key sources must be set
+    pub signing_key: Option<Felt>,
+    /// File holding the private key as a hex string
+    pub key_file: Option<PathBuf>,
+    /// Encrypted Starknet keystore holding the private key
+    pub keystore: Option<KeystoreConfig>,
+    /// Signer holding the private key outside of gatling
+    pub external_signer: Option<ExternalSignerConfig>,
     pub legacy_account: bool,
 }
 
+impl DeployerConfig {
+    pub fn validate(&self) -> Result<()> {
+        let sources = [
+            self.signing_key.is_some(),
+            self.key_file.is_some(),
+            self.keystore.is_some(),
+            self.external_signer.is_some(),
+        ];
+
+        ensure!(
+            sources.into_iter().filter(|&set| set).count() == 1,
+            "The deployer needs exactly one of `signing_key`, `key_file`, `keystore` and `external_signer`"
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeystoreConfig {
+    pub path: PathBuf,
+    /// File holding the password, which is prompted for when not set
+    pub password_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalSignerConfig {
+    /// Signer answering POST requests at `url`
+    Http { url: String },
+    /// Signer started as a child process, answering requests on its standard output
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct RunConfig {
     pub concurrency: u64,
     pub shooters: Vec<Shooters>,
     pub read_benches: Vec<ReadBenchConfig>,
+    #[serde(default)]
+    pub subscriptions: Vec<SubscriptionBenchConfig>,
+    /// The attack stops once the transactions of the blocks included during the attack paid more fees than this
+    #[serde(default, deserialize_with = "optional_amount_deserializer")]
+    pub max_total_fee: Option<Felt>,
+    /// Set from the command line to run against public chains
+    #[serde(skip)]
+    pub allow_public_chains: bool,
+}
+
+impl RunConfig {
+    /// Keep the shooters, read benches and subscriptions whose name matches one of the `only`
+    /// patterns, or all of them when there are none, and that match none of the `skip` patterns
+    pub fn select(&mut self, only: &[String], skip: &[String]) {
+        let selected = |name: &str| {
+            let matches = |patterns: &[String]| patterns.iter().any(|p| glob_match(p, name));
+            (only.is_empty() || matches(only)) && !matches(skip)
+        };
+
+        for pattern in only.iter().chain(skip) {
+            let names = self
+                .shooters
+                .iter()
+                .map(|shooter| &shooter.name)
+                .chain(self.read_benches.iter().map(|bench| &bench.name))
+                .chain(self.subscriptions.iter().map(|bench| &bench.name));
+
+            if !names.into_iter().any(|name| glob_match(pattern, name)) {
+                tracing::warn!("`{pattern}` matches no benchmark of the configuration");
+            }
+        }
+
+        self.shooters.retain(|shooter| selected(&shooter.name));
+        self.read_benches.retain(|bench| selected(&bench.name));
+        self.subscriptions.retain(|bench| selected(&bench.name));
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Shooters {
     pub name: String,
     pub shoot: u64,
+    #[serde(default)]
+    pub calls_per_tx: CallsPerTx,
+    /// Contract used by shooters that work over an arbitrary class
+    pub contract: Option<ContractSourceConfig>,
+    /// Constructor arguments used by shooters that deploy `contract`
+    pub constructor_calldata: Option<Vec<Felt>>,
+    /// Amount of work done by each transaction of shooters with a tunable workload
+    pub work: Option<u64>,
+    /// Relative weights of the kinds of transactions sent by the `invalid` shooter
+    pub mix: Option<BTreeMap<InvalidTransactionKind, u64>>,
+    /// Page sizes of the `starknet_getEvents` queries verifying the `events` shooter
+    pub chunk_sizes: Option<Vec<u64>>,
+    /// Submit transactions as JSON-RPC batches of this many transactions
+    pub batch_size: Option<u64>,
+}
+
+/// Kinds of transactions sent by the `invalid` shooter
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidTransactionKind {
+    Valid,
+    InvalidSignature,
+    StaleNonce,
+    FutureNonce,
+    InsufficientMaxFee,
+    UnknownSelector,
+    OversizedCalldata,
+}
+
+impl InvalidTransactionKind {
+    pub const ALL: [Self; 7] = [
+        Self::Valid,
+        Self::InvalidSignature,
+        Self::StaleNonce,
+        Self::FutureNonce,
+        Self::InsufficientMaxFee,
+        Self::UnknownSelector,
+        Self::OversizedCalldata,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Valid => "Valid",
+            Self::InvalidSignature => "Invalid Signature",
+            Self::StaleNonce => "Stale Nonce",
+            Self::FutureNonce => "Future Nonce",
+            Self::InsufficientMaxFee => "Insufficient Max Fee",
+            Self::UnknownSelector => "Unknown Selector",
+            Self::OversizedCalldata => "Oversized Calldata",
+        }
+    }
+}
+
+/// Amount of calls bundled in each invoke transaction of a shooter,
+/// either a fixed amount or drawn uniformly from an inclusive range
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum CallsPerTx {
+    Fixed(u64),
+    Range { min: u64, max: u64 },
+}
+
+impl Default for CallsPerTx {
+    fn default() -> Self {
+        Self::Fixed(1)
+    }
+}
+
+impl CallsPerTx {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            CallsPerTx::Fixed(amount) => {
+                ensure!(amount > 0, "calls_per_tx must be at least 1");
+            }
+            CallsPerTx::Range { min, max } => {
+                ensure!(min > 0, "calls_per_tx minimum must be at least 1");
+                ensure!(
+                    min <= max,
+                    "calls_per_tx minimum ({min}) is greater than its maximum ({max})"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws the amount of calls for the next transaction
+    pub fn sample(&self) -> u64 {
+        match *self {
+            CallsPerTx::Fixed(amount) => amount,
+            CallsPerTx::Range { min, max } => rand::thread_rng().gen_range(min..=max),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -125,44 +372,188 @@ pub struct ReadBenchConfig {
     pub name: String,
     pub num_requests: u64,
     pub method: JsonRpcMethod,
-    #[serde(deserialize_with = "parameters_file_deserializer")]
-    pub parameters_location: ParametersFile,
+    #[serde(default, deserialize_with = "parameters_file_deserializer")]
+    pub parameters_location: Option<ParametersFile>,
+    /// Generates fresh parameters for each request instead of reading them from a file
+    pub parameters_generator: Option<ParametersGeneratorConfig>,
+    /// Send requests as JSON-RPC batches of this many requests
+    pub batch_size: Option<u64>,
+    #[serde(default)]
+    pub transport: Transport,
+    /// Fields left out when comparing responses of different nodes
+    #[serde(default)]
+    pub ignore_fields: Vec<String>,
 }
 
-pub type ParametersFile = Vec<Map<String, Value>>;
+/// How read benches talk to the node
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    #[default]
+    Http,
+    /// Over `rpc.ws_url`, with one connection per concurrent user
+    Websocket,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubscriptionBenchConfig {
+    pub name: String,
+    pub method: SubscriptionMethod,
+    /// How many concurrent subscribers to open, each with its own connection
+    pub subscribers: u64,
+    /// How long to listen for notifications, in seconds
+    pub duration: u64,
+    /// Parameters of the subscription request
+    #[serde(default)]
+    pub params: Map<String, Value>,
+}
+
+/// Starknet WebSocket subscriptions
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionMethod {
+    NewHeads,
+    Events,
+    PendingTransactions,
+    TransactionStatus,
+}
+
+impl SubscriptionMethod {
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::NewHeads => "starknet_subscribeNewHeads",
+            Self::Events => "starknet_subscribeEvents",
+            Self::PendingTransactions => "starknet_subscribePendingTransactions",
+            Self::TransactionStatus => "starknet_subscribeTransactionStatus",
+        }
+    }
+}
+
+/// Parameters of each request, a list is sent as is while anything else is sent as the only parameter
+pub type ParametersFile = Vec<Value>;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ParametersGeneratorConfig {
+    /// Parameters object whose placeholder strings are replaced for each request,
+    /// see [`crate::generators::parameters`] for the supported placeholders
+    pub template: Value,
+    /// Amount of latest blocks the data is sampled from
+    #[serde(default = "default_generator_blocks")]
+    pub blocks: u64,
+    /// Storage keys `$storage_key` is sampled from
+    #[serde(default)]
+    pub storage_keys: Vec<Felt>,
+}
+
+fn default_generator_blocks() -> u64 {
+    100
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ReportConfig {
     pub num_blocks: u64,
     pub output_location: PathBuf,
+    /// Where `shoot` saves its artifacts and `read` loads them from
+    pub artifacts_location: Option<PathBuf>,
+    /// Measure how long submitted transactions take to show up on other nodes
+    pub propagation: Option<PropagationConfig>,
+    /// Check every response against the Starknet RPC spec
+    #[serde(default)]
+    pub validate_responses: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PropagationConfig {
+    /// Nodes polled for each submitted transaction
+    pub observers: Vec<String>,
+    /// Time between two polls of a transaction, in milliseconds
+    #[serde(default = "default_propagation_interval")]
+    pub interval: u64,
+}
+
+fn default_propagation_interval() -> u64 {
+    100
+}
+
+/// Values set from the command line, taking precedence over the file and the environment
+#[derive(Debug, Default, Clone)]
+pub struct ConfigOverrides {
+    /// Configuration keys such as `run.concurrency` and their values
+    pub values: Vec<(String, String)>,
+    /// Amount of transactions of shooters by name, shooters missing from the file are added
+    pub shooters: Vec<(String, u64)>,
 }
 
 impl GatlingConfig {
     /// Create a new configuration from environment variables.
     pub fn new() -> Result<Self> {
-        base_config_builder()
-            .build()
-            .unwrap()
-            .try_deserialize()
-            .map_err(|e| e.into())
+        Self::load(None, &ConfigOverrides::default())
     }
 
     /// Create a new configuration from a file.
     pub fn from_file(path: &str) -> Result<Self> {
-        base_config_builder()
-            .add_source(config::File::with_name(path))
-            .build()
-            .unwrap()
-            .try_deserialize()
-            .map_err(|e| e.into())
+        Self::load(Some(path), &ConfigOverrides::default())
+    }
+
+    /// Create a new configuration from environment variables and an optional file,
+    /// with `overrides` applied on top
+    pub fn load(path: Option<&str>, overrides: &ConfigOverrides) -> Result<Self> {
+        let mut builder = Config::builder();
+
+        if let Some(path) = path {
+            builder = builder.add_source(config::File::with_name(path));
+        }
+
+        // The environment takes precedence over the file
+        builder = builder.add_source(environment());
+
+        for (key, value) in &overrides.values {
+            builder = builder.set_override(key, value.as_str())?;
+        }
+
+        if !overrides.shooters.is_empty() {
+            #[derive(Deserialize)]
+            struct Named {
+                name: String,
+            }
+
+            // Shooters are overridden by their index in the list
+            let mut names: Vec<String> = builder
+                .clone()
+                .build()?
+                .get::<Option<Vec<Named>>>("run.shooters")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|shooter| shooter.name)
+                .collect();
+
+            for (name, shoot) in &overrides.shooters {
+                let index = match names.iter().position(|existing| existing == name) {
+                    Some(index) => index,
+                    None => {
+                        builder = builder.set_override(
+                            format!("run.shooters[{}].name", names.len()),
+                            name.as_str(),
+                        )?;
+                        names.push(name.clone());
+                        names.len() - 1
+                    }
+                };
+
+                builder = builder.set_override(format!("run.shooters[{index}].shoot"), *shoot)?;
+            }
+        }
+
+        Ok(builder.build()?.try_deserialize()?)
     }
 }
 
-fn base_config_builder() -> ConfigBuilder<DefaultState> {
-    Config::builder()
-        // Add in settings from the environment (with a prefix of GATLING)
-        // Eg.. `GATLING_FAIL_FAST=1 ./target/app` would set the `fail_fast` key
-        .add_source(config::Environment::with_prefix("gatling"))
+/// Settings from the environment, prefixed with `GATLING_` and nested with `__`
+/// Eg.. `GATLING_RUN__CONCURRENCY=50 ./target/app` would set the `run.concurrency` key
+fn environment() -> config::Environment {
+    config::Environment::with_prefix("gatling")
+        .prefix_separator("_")
+        .separator("__")
 }
 
 fn from_str_deserializer<'de, D>(deserializer: D) -> Result<Felt, D::Error>
@@ -176,15 +567,180 @@ where
     })
 }
 
-fn parameters_file_deserializer<'de, D>(de: D) -> Result<ParametersFile, D::Error>
+fn optional_from_str_deserializer<'de, D>(deserializer: D) -> Result<Option<Felt>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    from_str_deserializer(deserializer).map(Some)
+}
+
+/// Amounts are decimal or `0x` prefixed hex strings, or integers
+fn optional_amount_deserializer<'de, D>(deserializer: D) -> Result<Option<Felt>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Amount {
+        Integer(u64),
+        String(String),
+    }
+
+    let amount = match Option::<Amount>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Amount::Integer(amount)) => Felt::from(amount),
+        Some(Amount::String(amount)) => match amount.strip_prefix("0x") {
+            Some(_) => Felt::from_hex(&amount),
+            None => Felt::from_dec_str(&amount),
+        }
+        .map_err(|_| D::Error::custom(format!("invalid amount `{amount}`")))?,
+    };
+
+    Ok(Some(amount))
+}
+
+fn parameters_file_deserializer<'de, D>(de: D) -> Result<Option<ParametersFile>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let path = PathBuf::deserialize(de)?;
 
-    let file = File::open(path).expect("Could not open file");
+    let file = File::open(&path).map_err(|e| {
+        D::Error::custom(format!(
+            "Could not open parameters file {}: {e}",
+            path.display()
+        ))
+    })?;
     let reader = BufReader::new(file);
-    let params =
-        serde_json::from_reader(reader).expect("Could not deserialize read params correctly");
-    Ok(params)
+    let params = serde_json::from_reader(reader).map_err(|e| {
+        D::Error::custom(format!(
+            "Could not deserialize parameters file {}: {e}",
+            path.display()
+        ))
+    })?;
+    Ok(Some(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use clap::Parser;
+
+    use crate::cli::Cli;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+rpc:
+  url: "http://localhost:9944"
+
+setup:
+  erc20_contract:
+    v0: contracts/v0/ERC20.json
+  erc721_contract:
+    v0: contracts/v0/ERC721.json
+  account_contract:
+    v0: contracts/v0/OpenzeppelinAccount.json
+  fee_token_address: "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7"
+  num_accounts: 1
+
+run:
+  concurrency: 5
+  shooters:
+    - name: "transfer"
+      shoot: 100
+    - name: "mint"
+      shoot: 300
+  read_benches: []
+
+report:
+  num_blocks: 4
+  output_location: "report"
+
+deployer:
+  salt: "0x1"
+  address: "0x4"
+  signing_key: "0x1"
+  legacy_account: false
+"#;
+
+    /// Load the test configuration, written to a file named after `test`, with the command line `args`
+    fn load(test: &str, args: &[&str]) -> GatlingConfig {
+        let path = env::temp_dir().join(format!("gatling-{}-{test}.yaml", std::process::id()));
+        fs::write(&path, CONFIG).unwrap();
+
+        let path = path.to_str().unwrap();
+        let cli =
+            Cli::try_parse_from([&["gatling", "check", "--config-path", path], args].concat())
+                .unwrap();
+
+        let config = GatlingConfig::load(
+            cli.global_opts.config_path.as_deref(),
+            &cli.global_opts.overrides(),
+        );
+
+        fs::remove_file(path).unwrap();
+        config.unwrap()
+    }
+
+    /// The only test touching the environment, so that the others do not depend on it
+    #[test]
+    fn sources_precedence() {
+        let config = load("file", &[]);
+        assert_eq!(config.run.concurrency, 5);
+        assert_eq!(config.report.num_blocks, 4);
+
+        env::set_var("GATLING_RUN__CONCURRENCY", "10");
+        env::set_var("GATLING_REPORT__NUM_BLOCKS", "6");
+
+        let environment = load("environment", &[]);
+        let set = load("set", &["--set", "run.concurrency=20"]);
+        let option = load(
+            "option",
+            &["--set", "run.concurrency=20", "--concurrency", "30"],
+        );
+
+        env::remove_var("GATLING_RUN__CONCURRENCY");
+        env::remove_var("GATLING_REPORT__NUM_BLOCKS");
+
+        assert_eq!(environment.run.concurrency, 10);
+        assert_eq!(environment.report.num_blocks, 6);
+
+        assert_eq!(set.run.concurrency, 20);
+        assert_eq!(set.report.num_blocks, 6);
+
+        assert_eq!(option.run.concurrency, 30);
+    }
+
+    #[test]
+    fn dedicated_options() {
+        let config = load(
+            "dedicated",
+            &[
+                "--rpc-url",
+                "http://node:9944/rpc/v0_7",
+                "--output",
+                "reports/run",
+            ],
+        );
+
+        assert_eq!(config.rpc.url, "http://node:9944/rpc/v0_7");
+        assert_eq!(config.report.output_location, PathBuf::from("reports/run"));
+    }
+
+    #[test]
+    fn shooter_overrides() {
+        let config = load("shooters", &["--shooter", "mint=7", "--shooter", "udc=3"]);
+
+        let shooters: Vec<_> = config
+            .run
+            .shooters
+            .iter()
+            .map(|shooter| (shooter.name.as_str(), shooter.shoot))
+            .collect();
+
+        // Overridden in place, shooters missing from the file come last
+        assert_eq!(shooters, [("transfer", 100), ("mint", 7), ("udc", 3)]);
+    }
 }
@@ -1,3 +1,5 @@
+pub mod parameters;
+
 use rand::Rng;
 
 use starknet::{
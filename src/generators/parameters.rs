@@ -0,0 +1,357 @@
+//! Parameters of read benchmarks, either read from a file or generated for each request.
+//!
+//! Parameters that are a list are sent as is, anything else is sent as the only parameter.
+//!
+//! Generated parameters fill a template where these string placeholders are replaced:
+//!
+//! - `$block_number`: A block number sampled uniformly over `[latest - blocks, latest]`
+//! - `$block_hash`: The hash of a block in the same range
+//! - `$transaction_hash`: The hash of a transaction included in the same range
+//! - `$contract_address`: An address whose state changed in the same range
+//! - `$storage_key`: A key from the configured `storage_keys`
+//!
+//! And these ones from the [`Artifacts`] saved by the last `shoot`:
+//!
+//! - `$sent_transaction_hash`: The hash of a transaction sent by the shooters
+//! - `$account_address`: The address of an account used by the shooters
+//! - `$<kind>_address`: The address of a contract of that kind deployed by the shooters, such as `$erc20_address`
+
+use std::{collections::BTreeSet, ops::RangeInclusive, sync::Arc};
+
+use color_eyre::eyre::{bail, ensure};
+use rand::{seq::SliceRandom, Rng};
+use serde_json::Value;
+use starknet::{
+    core::types::{BlockId, Felt, MaybePendingBlockWithTxHashes, MaybePendingStateUpdate},
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+};
+
+use crate::{
+    artifacts::Artifacts,
+    config::{ParametersFile, ParametersGeneratorConfig, ReadBenchConfig},
+};
+
+const BLOCK_NUMBER: &str = "$block_number";
+const BLOCK_HASH: &str = "$block_hash";
+const TRANSACTION_HASH: &str = "$transaction_hash";
+const CONTRACT_ADDRESS: &str = "$contract_address";
+const STORAGE_KEY: &str = "$storage_key";
+const SENT_TRANSACTION_HASH: &str = "$sent_transaction_hash";
+const ACCOUNT_ADDRESS: &str = "$account_address";
+
+/// Where each request of a read benchmark gets its parameters from
+#[derive(Clone)]
+pub enum ReadParameters {
+    File(Arc<ParametersFile>),
+    Generated(Arc<GeneratedParameters>),
+}
+
+impl ReadParameters {
+    pub async fn from_config(
+        rpc: &JsonRpcClient<HttpTransport>,
+        config: &ReadBenchConfig,
+        artifacts: Option<&Artifacts>,
+    ) -> color_eyre::Result<Self> {
+        match (&config.parameters_location, &config.parameters_generator) {
+            (Some(parameters), None) => {
+                ensure!(
+                    !parameters.is_empty(),
+                    "Read bench `{}` has an empty parameters file",
+                    config.name
+                );
+                Ok(Self::File(Arc::new(parameters.clone())))
+            }
+            (None, Some(generator)) => Ok(Self::Generated(Arc::new(
+                GeneratedParameters::new(rpc, generator, artifacts).await?,
+            ))),
+            _ => bail!(
+                "Read bench `{}` needs exactly one of `parameters_location` and `parameters_generator`",
+                config.name
+            ),
+        }
+    }
+
+    /// Parameters of the next request
+    pub fn sample(&self) -> Value {
+        let mut rng = rand::thread_rng();
+
+        match self {
+            Self::File(parameters) => parameters
+                .choose(&mut rng)
+                .expect("Parameters file is not empty")
+                .clone(),
+            Self::Generated(generated) => generated.sample(&mut rng),
+        }
+    }
+}
+
+/// Data sampled from recent blocks to fill the parameters template
+pub struct GeneratedParameters {
+    template: Value,
+    block_numbers: RangeInclusive<u64>,
+    block_hashes: Vec<Felt>,
+    transaction_hashes: Vec<Felt>,
+    contract_addresses: Vec<Felt>,
+    storage_keys: Vec<Felt>,
+    artifacts: Artifacts,
+}
+
+impl GeneratedParameters {
+    pub async fn new(
+        rpc: &JsonRpcClient<HttpTransport>,
+        config: &ParametersGeneratorConfig,
+        artifacts: Option<&Artifacts>,
+    ) -> color_eyre::Result<Self> {
+        let template = config.template.clone();
+        let used = placeholders(&template);
+        let uses = |placeholder| used.contains(&placeholder);
+
+        let latest = rpc.block_number().await?;
+        let block_numbers = latest.saturating_sub(config.blocks)..=latest;
+
+        let mut block_hashes = Vec::new();
+        let mut transaction_hashes = Vec::new();
+        let mut contract_addresses = Vec::new();
+
+        if uses(BLOCK_HASH) || uses(TRANSACTION_HASH) {
+            tracing::info!("Sampling block and transaction hashes from blocks {block_numbers:?}");
+
+            for block_number in block_numbers.clone() {
+                if let MaybePendingBlockWithTxHashes::Block(block) = rpc
+                    .get_block_with_tx_hashes(BlockId::Number(block_number))
+                    .await?
+                {
+                    block_hashes.push(block.block_hash);
+                    transaction_hashes.extend(block.transactions);
+                }
+            }
+        }
+
+        if uses(CONTRACT_ADDRESS) {
+            tracing::info!(
+                "Sampling contract addresses from state diffs of blocks {block_numbers:?}"
+            );
+
+            for block_number in block_numbers.clone() {
+                if let MaybePendingStateUpdate::Update(update) =
+                    rpc.get_state_update(BlockId::Number(block_number)).await?
+                {
+                    let diff = update.state_diff;
+                    contract_addresses.extend(diff.storage_diffs.iter().map(|d| d.address));
+                    contract_addresses.extend(diff.deployed_contracts.iter().map(|d| d.address));
+                    contract_addresses.extend(diff.nonces.iter().map(|n| n.contract_address));
+                }
+            }
+
+            contract_addresses.sort();
+            contract_addresses.dedup();
+        }
+
+        let artifacts = artifacts.cloned().unwrap_or_default();
+
+        for &placeholder in &used {
+            let pool = match placeholder {
+                BLOCK_NUMBER => continue,
+                BLOCK_HASH => &block_hashes,
+                TRANSACTION_HASH => &transaction_hashes,
+                CONTRACT_ADDRESS => &contract_addresses,
+                STORAGE_KEY => &config.storage_keys,
+                SENT_TRANSACTION_HASH => &artifacts.transactions,
+                ACCOUNT_ADDRESS => &artifacts.accounts,
+                other => {
+                    match artifact_kind(other).and_then(|kind| artifacts.contracts.get(kind)) {
+                        Some(pool) => pool,
+                        None => bail!("Unknown placeholder `{other}`"),
+                    }
+                }
+            };
+
+            ensure!(
+                !pool.is_empty(),
+                "Nothing to sample `{placeholder}` from, placeholders of the write phase need `report.artifacts_location`"
+            );
+        }
+
+        Ok(Self {
+            template: config.template.clone(),
+            block_numbers,
+            block_hashes,
+            transaction_hashes,
+            contract_addresses,
+            storage_keys: config.storage_keys.clone(),
+            artifacts,
+        })
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Value {
+        self.fill(&self.template, rng)
+    }
+
+    fn fill(&self, value: &Value, rng: &mut impl Rng) -> Value {
+        let felt = |pool: &[Felt], rng: &mut _| {
+            let felt = pool
+                .choose(rng)
+                .expect("Pools of used placeholders are not empty");
+            Value::String(format!("{felt:#x}"))
+        };
+
+        match value {
+            Value::String(s) => match s.as_str() {
+                BLOCK_NUMBER => Value::from(rng.gen_range(self.block_numbers.clone())),
+                BLOCK_HASH => felt(&self.block_hashes, rng),
+                TRANSACTION_HASH => felt(&self.transaction_hashes, rng),
+                CONTRACT_ADDRESS => felt(&self.contract_addresses, rng),
+                STORAGE_KEY => felt(&self.storage_keys, rng),
+                SENT_TRANSACTION_HASH => felt(&self.artifacts.transactions, rng),
+                ACCOUNT_ADDRESS => felt(&self.artifacts.accounts, rng),
+                other => match artifact_kind(other)
+                    .and_then(|kind| self.artifacts.contracts.get(kind))
+                    .filter(|pool| !pool.is_empty())
+                {
+                    Some(pool) => felt(pool, rng),
+                    None => value.clone(),
+                },
+            },
+            Value::Array(values) => {
+                Value::Array(values.iter().map(|v| self.fill(v, rng)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), self.fill(value, rng)))
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+}
+
+/// Placeholders of `template` that are none of the supported ones,
+/// whether the artifacts hold the contracts of `$<kind>_address` is only known once generating
+pub fn unknown_placeholders(template: &Value) -> Vec<&str> {
+    const KNOWN: [&str; 7] = [
+        BLOCK_NUMBER,
+        BLOCK_HASH,
+        TRANSACTION_HASH,
+        CONTRACT_ADDRESS,
+        STORAGE_KEY,
+        SENT_TRANSACTION_HASH,
+        ACCOUNT_ADDRESS,
+    ];
+
+    placeholders(template)
+        .into_iter()
+        .filter(|placeholder| {
+            !KNOWN.contains(placeholder)
+                && artifact_kind(placeholder).map_or(true, |kind| kind.is_empty())
+        })
+        .collect()
+}
+
+/// Kind of contract a `$<kind>_address` placeholder refers to
+fn artifact_kind(placeholder: &str) -> Option<&str> {
+    placeholder.strip_prefix('$')?.strip_suffix("_address")
+}
+
+/// All the strings of the template that look like placeholders
+fn placeholders(value: &Value) -> BTreeSet<&str> {
+    match value {
+        Value::String(s) if s.starts_with('$') => BTreeSet::from([s.as_str()]),
+        Value::Array(values) => values.iter().flat_map(placeholders).collect(),
+        Value::Object(map) => map.values().flat_map(placeholders).collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::*;
+
+    /// Parameters with a single value to sample for every placeholder but `$block_hash`
+    fn parameters(template: Value) -> GeneratedParameters {
+        GeneratedParameters {
+            template,
+            block_numbers: 7..=7,
+            block_hashes: vec![Felt::from(0xb1), Felt::from(0xb2)],
+            transaction_hashes: vec![Felt::from(0x71)],
+            contract_addresses: vec![Felt::from(0xc1)],
+            storage_keys: vec![Felt::from(0x51)],
+            artifacts: Artifacts {
+                transactions: vec![Felt::from(0xa71)],
+                accounts: vec![Felt::from(0xacc)],
+                contracts: BTreeMap::from([("erc20".to_owned(), vec![Felt::from(0xe20)])]),
+            },
+        }
+    }
+
+    #[test]
+    fn known_placeholders_are_filled() {
+        let parameters = parameters(json!({
+            "block_id": { "block_number": "$block_number" },
+            "keys": ["$storage_key", "0x1"],
+            "hashes": ["$transaction_hash", "$sent_transaction_hash"],
+            "addresses": ["$contract_address", "$account_address", "$erc20_address"],
+            "chunk_size": 10,
+        }));
+
+        assert_eq!(
+            parameters.sample(&mut StdRng::seed_from_u64(0)),
+            json!({
+                "block_id": { "block_number": 7 },
+                "keys": ["0x51", "0x1"],
+                "hashes": ["0x71", "0xa71"],
+                "addresses": ["0xc1", "0xacc", "0xe20"],
+                "chunk_size": 10,
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_as_is() {
+        let template = json!(["$block_number", "$unknown", "$erc721_address"]);
+
+        // Whether there are erc721 contracts is only known from the artifacts
+        assert_eq!(
+            parameters(template).sample(&mut StdRng::seed_from_u64(0)),
+            json!([7, "$unknown", "$erc721_address"])
+        );
+    }
+
+    #[test]
+    fn repeated_placeholders_are_sampled_each_time() {
+        let parameters = parameters(json!(["$block_hash", "$block_hash"]));
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let samples: Vec<_> = (0..32).map(|_| parameters.sample(&mut rng)).collect();
+
+        for sample in &samples {
+            for hash in sample.as_array().unwrap() {
+                assert!(hash == "0xb1" || hash == "0xb2", "{hash}");
+            }
+        }
+
+        assert!(samples.iter().any(|sample| sample[0] != sample[1]));
+    }
+
+    #[test]
+    fn unknown_placeholders_of_a_template() {
+        let template = json!({
+            "block_id": { "block_hash": "$block_hash" },
+            "address": "$erc20_address",
+            "keys": ["$unknown", "$storage_key", "$unknown", "$_address"],
+            "other": "$other",
+            "plain": "not a placeholder",
+        });
+
+        assert_eq!(
+            unknown_placeholders(&template),
+            ["$_address", "$other", "$unknown"]
+        );
+
+        assert!(unknown_placeholders(&json!(["$block_number", "$block_number"])).is_empty());
+    }
+}
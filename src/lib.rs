@@ -9,11 +9,17 @@ pub mod config;
 /// Actions.
 pub mod actions;
 
+/// Artifacts of the write shooters.
+pub mod artifacts;
+
 /// Generators.
 pub mod generators;
 
 /// Metrics.
 pub mod metrics;
 
+/// Starknet RPC specification.
+pub mod spec;
+
 /// Utils.
 pub mod utils;
@@ -35,10 +35,13 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     // Retrieve the application configuration.
-    let cfg = match cli.global_opts.config_path {
-        Some(path) => GatlingConfig::from_file(&path)?,
-        None => GatlingConfig::new()?,
-    };
+    let mut cfg = GatlingConfig::load(
+        cli.global_opts.config_path.as_deref(),
+        &cli.global_opts.overrides(),
+    )?;
+
+    cfg.run.allow_public_chains = cli.global_opts.i_know_what_im_doing;
+    cfg.run.select(&cli.global_opts.only, &cli.global_opts.skip);
 
     // Execute the command.
     match cli.command {
@@ -48,6 +51,24 @@ async fn main() -> Result<()> {
         Command::Read { .. } => {
             actions::read(cfg).await?;
         }
+        Command::ShootAndRead { .. } => {
+            actions::shoot_and_read(cfg).await?;
+        }
+        Command::Subscribe { .. } => {
+            actions::subscribe(cfg).await?;
+        }
+        Command::DiffRead { .. } => {
+            actions::diff_read(cfg).await?;
+        }
+        Command::Teardown { .. } => {
+            actions::teardown(cfg).await?;
+        }
+        Command::Check { .. } => {
+            actions::check(cfg)?;
+        }
+        Command::List { .. } => {
+            actions::list(&cfg);
+        }
     }
 
     Ok(())
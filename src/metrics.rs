@@ -1,23 +1,28 @@
 use crate::utils::get_blocks_with_txs;
 
-use color_eyre::{eyre::OptionExt, Result};
+use color_eyre::{
+    eyre::{ensure, OptionExt},
+    Result,
+};
 
 use goose::metrics::{GooseMetrics, GooseRequestMetricAggregate, GooseRequestMetricTimingData};
 use serde::Serialize;
 use starknet::{
     core::types::{
         BlockWithTxs, ExecutionResources, InvokeTransaction, InvokeTransactionV0,
-        InvokeTransactionV1, L1HandlerTransaction, Transaction,
+        InvokeTransactionV1, InvokeTransactionV3, Transaction,
     },
     providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
 };
-use std::{borrow::Cow, fmt, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, fmt, sync::Arc};
 
 pub const BLOCK_TIME: u64 = 6;
 
 #[derive(Clone, Debug, Serialize)]
 pub struct GlobalReport {
     pub users: u64,
+    /// RPC spec version of the node under test
+    pub spec_version: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub all_bench_report: Option<BenchmarkReport>,
     pub benches: Vec<BenchmarkReport>,
@@ -40,7 +45,7 @@ pub struct NodeMetrics {
     pub compute: fn(&[u64]) -> f64,
 }
 
-const GOOSE_TIME_UNIT: &str = "milliseconds";
+pub const GOOSE_TIME_UNIT: &str = "milliseconds";
 
 /// A struct that contains the result of a metric computation alognside the name and unit
 /// This struct is used for displaying the metric result
@@ -68,6 +73,37 @@ pub struct BenchmarkReport {
     pub last_x_blocks_metrics: Option<LastXBlocksMetric>,
 }
 
+/// How the items of JSON-RPC batches went, attributing each error to its item
+#[derive(Debug, Default)]
+pub struct BatchStats {
+    pub batches: u64,
+    pub items: u64,
+    pub failed_items: u64,
+    /// Total time taken by the batches
+    pub time: u64,
+    /// Amount of failed items and message per JSON-RPC error code
+    pub errors: BTreeMap<i64, (String, u64)>,
+}
+
+impl BatchStats {
+    pub fn record_batch(&mut self, time: u64) {
+        self.batches += 1;
+        self.time += time;
+    }
+
+    pub fn record_item(&mut self, error: Option<(i64, &str)>) {
+        self.items += 1;
+
+        if let Some((code, message)) = error {
+            self.failed_items += 1;
+            self.errors
+                .entry(code)
+                .or_insert_with(|| (message.to_string(), 0))
+                .1 += 1;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct LastXBlocksMetric {
     pub num_blocks: u64,
@@ -90,6 +126,11 @@ impl BenchmarkReport {
         mut start_block: u64,
         mut end_block: u64,
     ) -> Result<()> {
+        ensure!(
+            start_block <= end_block,
+            "Block range {start_block}..={end_block} is empty"
+        );
+
         // Whenever possible, skip the first and last blocks from the metrics
         // to make sure all the blocks used for calculating metrics are full
         if end_block - start_block > 2 {
@@ -125,6 +166,48 @@ impl BenchmarkReport {
         Ok(())
     }
 
+    /// Adds the average and maximum amount of `name` per block over a block range,
+    /// `count` returns how many of them a single transaction accounts for
+    pub async fn with_per_block_count(
+        &mut self,
+        starknet_rpc: &Arc<JsonRpcClient<HttpTransport>>,
+        start_block: u64,
+        end_block: u64,
+        name: &str,
+        unit: &'static str,
+        count: impl Fn(&Transaction) -> u64,
+    ) -> Result<()> {
+        let blocks_with_txs = get_blocks_with_txs(starknet_rpc, start_block..=end_block).await?;
+
+        let per_block: Vec<u64> = blocks_with_txs
+            .iter()
+            .map(|(block, _)| block.transactions.iter().map(&count).sum())
+            .collect();
+
+        self.with_per_block_amounts(name, unit, &per_block);
+
+        Ok(())
+    }
+
+    /// Adds the average and maximum of amounts of `name` that were counted per block
+    pub fn with_per_block_amounts(&mut self, name: &str, unit: &'static str, per_block: &[u64]) {
+        let total: u64 = per_block.iter().sum();
+        let max = per_block.iter().max().copied().unwrap_or_default();
+
+        self.metrics.extend_from_slice(&[
+            MetricResult {
+                name: format!("Average {name} per block").into(),
+                unit,
+                value: (total as f64 / per_block.len() as f64).into(),
+            },
+            MetricResult {
+                name: format!("Max {name} per block").into(),
+                unit,
+                value: max.into(),
+            },
+        ]);
+    }
+
     pub fn with_goose_write_metrics(&mut self, metrics: &GooseMetrics) -> Result<()> {
         let submission_requests = metrics
             .requests
@@ -153,6 +236,109 @@ impl BenchmarkReport {
         Ok(())
     }
 
+    /// Latency metrics over raw times in milliseconds, for requests that do not go through goose
+    pub fn with_latencies(&mut self, times: &mut [u64], failed: u64, metric: Option<&str>) {
+        let name = |template: &str| match metric {
+            Some(metric) => Cow::Owned(format!("{metric} {template}")),
+            None => Cow::Owned(template.to_string()),
+        };
+
+        times.sort_unstable();
+
+        if let (Some(&min), Some(&max)) = (times.first(), times.last()) {
+            let total: u64 = times.iter().sum();
+
+            self.metrics.extend_from_slice(&[
+                MetricResult {
+                    name: name("Total Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: total.into(),
+                },
+                MetricResult {
+                    name: name("Max Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: max.into(),
+                },
+                MetricResult {
+                    name: name("Min Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: min.into(),
+                },
+                MetricResult {
+                    name: name("Average Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (total as f64 / times.len() as f64).into(),
+                },
+                MetricResult {
+                    name: name("P90 Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: times[times.len() * 90 / 100].into(),
+                },
+                MetricResult {
+                    name: name("P50 Time"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: times[times.len() * 50 / 100].into(),
+                },
+            ]);
+        }
+
+        if failed != 0 {
+            self.metrics.push(MetricResult {
+                name: name("Failed Requests"),
+                unit: "",
+                value: failed.into(),
+            });
+        }
+    }
+
+    pub fn with_batch_metrics(&mut self, stats: &BatchStats, metric: Option<&str>) {
+        let name = |template: &str| match metric {
+            Some(metric) => Cow::Owned(format!("{metric} {template}")),
+            None => Cow::Owned(template.to_string()),
+        };
+
+        self.metrics.extend_from_slice(&[
+            MetricResult {
+                name: name("Batches"),
+                unit: "batches",
+                value: stats.batches.into(),
+            },
+            MetricResult {
+                name: name("Batch Items"),
+                unit: "items",
+                value: stats.items.into(),
+            },
+            MetricResult {
+                name: name("Failed Batch Items"),
+                unit: "items",
+                value: stats.failed_items.into(),
+            },
+        ]);
+
+        if stats.batches != 0 && stats.items != 0 {
+            self.metrics.extend_from_slice(&[
+                MetricResult {
+                    name: name("Average Time per Batch"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (stats.time as f64 / stats.batches as f64).into(),
+                },
+                MetricResult {
+                    name: name("Average Time per Batch Item"),
+                    unit: GOOSE_TIME_UNIT,
+                    value: (stats.time as f64 / stats.items as f64).into(),
+                },
+            ]);
+        }
+
+        for (code, (message, amount)) in &stats.errors {
+            self.metrics.push(MetricResult {
+                name: name(&format!("Batch Item Error {code} ({message})")),
+                unit: "",
+                value: (*amount).into(),
+            });
+        }
+    }
+
     fn with_request_metric_aggregate(
         &mut self,
         requests: &GooseRequestMetricAggregate,
@@ -291,18 +477,24 @@ pub fn compute_node_metrics(
         },
     ];
 
+    let total_uops: u64 = blocks_with_txs
+        .iter()
+        .flat_map(|(b, _)| &b.transactions)
+        .map(tx_get_user_operations)
+        .sum();
+
+    // With multicalls a transaction bundles several user operations,
+    // so UOPS and TPS diverge by this ratio
+    metrics.push(MetricResult {
+        name: "Average Calls per Transaction".into(),
+        unit: "calls/transaction",
+        value: (total_uops as f64 / total_transactions as f64).into(),
+    });
+
     let (first_block, _) = blocks_with_txs.first().ok_or_eyre("No first block")?;
     let (last_block, _) = blocks_with_txs.last().ok_or_eyre("No last block")?;
 
     if first_block.timestamp != last_block.timestamp {
-        let total_uops: u64 = blocks_with_txs
-            .iter()
-            .flat_map(|(b, _)| &b.transactions)
-            .map(tx_get_user_operations)
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .sum();
-
         let total_steps: u64 = blocks_with_txs
             .iter()
             .flat_map(|(_, r)| r)
@@ -325,19 +517,18 @@ pub fn compute_node_metrics(
     Ok(metrics)
 }
 
-fn tx_get_user_operations(tx: &Transaction) -> Result<u64> {
-    Ok(match tx {
+/// Calls bundled in a transaction, which is the length of the multicall of account invokes.
+/// L1 handlers and other transactions count as a single operation
+fn tx_get_user_operations(tx: &Transaction) -> u64 {
+    match tx {
         Transaction::Invoke(
             InvokeTransaction::V0(InvokeTransactionV0 { calldata, .. })
-            | InvokeTransaction::V1(InvokeTransactionV1 { calldata, .. }),
-        )
-        | Transaction::L1Handler(L1HandlerTransaction { calldata, .. }) => {
-            let &user_operations = calldata
-                .first()
-                .ok_or_eyre("Expected calldata to have at least one field element")?;
-
-            user_operations.try_into()?
-        }
-        _ => 1, // Other txs can be considered as 1 uop
-    })
+            | InvokeTransaction::V1(InvokeTransactionV1 { calldata, .. })
+            | InvokeTransaction::V3(InvokeTransactionV3 { calldata, .. }),
+        ) => calldata
+            .first()
+            .and_then(|&calls| u64::try_from(calls).ok())
+            .unwrap_or(1),
+        _ => 1,
+    }
 }
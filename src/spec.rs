@@ -0,0 +1,687 @@
+//! Validation of RPC responses against the bundled Starknet OpenRPC specification
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::eyre::{bail, eyre, OptionExt};
+use regex::Regex;
+use serde_json::Value;
+use starknet::providers::jsonrpc::JsonRpcMethod;
+
+use crate::metrics::{BenchmarkReport, MetricResult};
+
+/// OpenRPC documents of each bundled spec version, by file name
+const SPECS: &[(&str, &[(&str, &str)])] = &[(
+    "0.7.1",
+    &[
+        (
+            "starknet_api_openrpc.json",
+            include_str!("../specs/0.7.1/starknet_api_openrpc.json"),
+        ),
+        (
+            "starknet_trace_api_openrpc.json",
+            include_str!("../specs/0.7.1/starknet_trace_api_openrpc.json"),
+        ),
+        (
+            "starknet_write_api.json",
+            include_str!("../specs/0.7.1/starknet_write_api.json"),
+        ),
+    ],
+)];
+
+/// Error codes any method may answer with, from the JSON-RPC specification
+const JSON_RPC_ERRORS: [i64; 5] = [-32700, -32600, -32601, -32602, -32603];
+
+static VALIDATOR: OnceLock<Validator> = OnceLock::new();
+
+/// The schemas of a spec version
+pub struct Spec {
+    version: &'static str,
+    documents: HashMap<&'static str, Value>,
+    methods: HashMap<String, Method>,
+    patterns: HashMap<String, Regex>,
+}
+
+struct Method {
+    /// Document the method is defined in, which its references are relative to
+    document: &'static str,
+    result: Value,
+    errors: Vec<i64>,
+}
+
+impl Spec {
+    /// Load the bundled spec of `version`, or of the same minor version
+    pub fn bundled(version: &str) -> color_eyre::Result<Self> {
+        let minor = |version: &str| version.rsplit_once('.').map(|(minor, _)| minor.to_owned());
+
+        let (version, files) = SPECS
+            .iter()
+            .find(|(bundled, _)| *bundled == version)
+            .or_else(|| {
+                SPECS
+                    .iter()
+                    .find(|(bundled, _)| minor(bundled) == minor(version))
+            })
+            .ok_or_else(|| eyre!("No bundled spec for RPC version {version}"))?;
+
+        let mut spec = Self {
+            version,
+            documents: HashMap::new(),
+            methods: HashMap::new(),
+            patterns: HashMap::new(),
+        };
+
+        for (name, content) in *files {
+            let document: Value = serde_json::from_str(content)?;
+            collect_patterns(&document, &mut spec.patterns)?;
+            spec.documents.insert(name, document);
+        }
+
+        let mut methods = HashMap::new();
+
+        for (&name, document) in &spec.documents {
+            let document_methods = document["methods"]
+                .as_array()
+                .ok_or_eyre("Spec document has no methods")?;
+
+            for method in document_methods {
+                let errors = match method["errors"].as_array() {
+                    Some(errors) => errors
+                        .iter()
+                        .map(|error| {
+                            let (_, error) = spec.resolve(name, error)?;
+                            error["code"].as_i64().ok_or_eyre("Spec error has no code")
+                        })
+                        .collect::<color_eyre::Result<_>>()?,
+                    None => Vec::new(),
+                };
+
+                methods.insert(
+                    method["name"]
+                        .as_str()
+                        .ok_or_eyre("Spec method has no name")?
+                        .to_owned(),
+                    Method {
+                        document: name,
+                        result: method["result"]["schema"].clone(),
+                        errors,
+                    },
+                );
+            }
+        }
+
+        spec.methods = methods;
+
+        Ok(spec)
+    }
+
+    pub fn version(&self) -> &'static str {
+        self.version
+    }
+
+    /// Whether the spec defines `method`, whose responses can then be checked
+    pub fn has_method(&self, method: &str) -> bool {
+        self.methods.contains_key(method)
+    }
+
+    /// Check a JSON-RPC response to `method`, returning the first violation found.
+    /// Methods the spec does not know about are not checked
+    pub fn check_response(&self, method: &str, response: &Value) -> Result<(), String> {
+        let Some(spec) = self.methods.get(method) else {
+            return Ok(());
+        };
+
+        if let Some(result) = response.get("result") {
+            return self.check(spec.document, &spec.result, result, "result");
+        }
+
+        let Some(error) = response.get("error") else {
+            return Err("Response has neither a result nor an error".into());
+        };
+
+        let Some(code) = error["code"].as_i64() else {
+            return Err("Error has no integer code".into());
+        };
+
+        if !error["message"].is_string() {
+            return Err(format!("Error {code} has no message"));
+        }
+
+        if !spec.errors.contains(&code) && !JSON_RPC_ERRORS.contains(&code) {
+            return Err(format!("Error code {code} is not one of the method errors"));
+        }
+
+        Ok(())
+    }
+
+    /// Check `value` at `path` against `schema`, found in `document`
+    fn check(
+        &self,
+        document: &'static str,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), String> {
+        let Value::Object(keywords) = schema else {
+            return Ok(());
+        };
+
+        if keywords.contains_key("$ref") {
+            let (document, schema) = self.resolve(document, schema).map_err(|e| e.to_string())?;
+            self.check(document, schema, value, path)?;
+        }
+
+        if let Some(Value::String(kind)) = keywords.get("type") {
+            let matches = match kind.as_str() {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "number" => value.is_number(),
+                "boolean" => value.is_boolean(),
+                "null" => value.is_null(),
+                _ => true,
+            };
+
+            if !matches {
+                return Err(format!("`{path}` should be of type {kind}, got {value}"));
+            }
+        }
+
+        if let Some(Value::Array(variants)) = keywords.get("enum") {
+            if !variants.contains(value) {
+                return Err(format!(
+                    "`{path}` should be one of {variants:?}, got {value}"
+                ));
+            }
+        }
+
+        if let Some(expected) = keywords.get("const") {
+            if expected != value {
+                return Err(format!("`{path}` should be {expected}, got {value}"));
+            }
+        }
+
+        if let (Some(Value::String(pattern)), Value::String(string)) =
+            (keywords.get("pattern"), value)
+        {
+            if !self.patterns[pattern].is_match(string) {
+                return Err(format!("`{path}` should match {pattern}, got {string}"));
+            }
+        }
+
+        if let (Some(minimum), Some(number)) = (
+            keywords.get("minimum").and_then(Value::as_f64),
+            value.as_f64(),
+        ) {
+            if number < minimum {
+                return Err(format!(
+                    "`{path}` should be at least {minimum}, got {number}"
+                ));
+            }
+        }
+
+        if let Value::Object(object) = value {
+            if let Some(Value::Array(required)) = keywords.get("required") {
+                for field in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(field) {
+                        return Err(format!("`{path}` is missing `{field}`"));
+                    }
+                }
+            }
+
+            if let Some(Value::Object(properties)) = keywords.get("properties") {
+                for (field, schema) in properties {
+                    if let Some(value) = object.get(field) {
+                        self.check(document, schema, value, &format!("{path}.{field}"))?;
+                    }
+                }
+            }
+        }
+
+        if let (Some(items), Value::Array(array)) = (keywords.get("items"), value) {
+            for (index, value) in array.iter().enumerate() {
+                self.check(document, items, value, &format!("{path}[{index}]"))?;
+            }
+        }
+
+        if let Some(Value::Array(schemas)) = keywords.get("allOf") {
+            for schema in schemas {
+                self.check(document, schema, value, path)?;
+            }
+        }
+
+        // Variants of the spec overlap as they allow additional fields,
+        // so matching more than one is not a violation
+        for keyword in ["oneOf", "anyOf"] {
+            if let Some(Value::Array(schemas)) = keywords.get(keyword) {
+                let mut errors = schemas
+                    .iter()
+                    .map(|schema| self.check(document, schema, value, path));
+
+                if !errors.any(|result| result.is_ok()) {
+                    return Err(format!(
+                        "`{path}` matches none of the variants, got {value}"
+                    ));
+                }
+            }
+        }
+
+        if let Some(schema) = keywords.get("not") {
+            if self.check(document, schema, value, path).is_ok() {
+                return Err(format!("`{path}` matches a forbidden schema, got {value}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follow the `$ref` of `schema`, which may point to another document of the spec
+    fn resolve<'a>(
+        &'a self,
+        document: &'static str,
+        schema: &'a Value,
+    ) -> color_eyre::Result<(&'static str, &'a Value)> {
+        let Some(reference) = schema["$ref"].as_str() else {
+            return Ok((document, schema));
+        };
+
+        let (file, pointer) = reference
+            .split_once('#')
+            .ok_or_else(|| eyre!("Reference {reference} has no pointer"))?;
+
+        let document = match file.rsplit('/').next() {
+            Some("") | None => document,
+            Some(file) => self
+                .documents
+                .keys()
+                .find(|name| **name == file)
+                .ok_or_else(|| eyre!("Reference {reference} is to an unknown document"))?,
+        };
+
+        let schema = self.documents[document]
+            .pointer(pointer)
+            .ok_or_else(|| eyre!("Reference {reference} does not resolve"))?;
+
+        if schema.get("$ref").is_some() {
+            return self.resolve(document, schema);
+        }
+
+        Ok((document, schema))
+    }
+}
+
+fn collect_patterns(
+    value: &Value,
+    patterns: &mut HashMap<String, Regex>,
+) -> color_eyre::Result<()> {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("pattern", Value::String(pattern)) => {
+                        if !patterns.contains_key(pattern) {
+                            patterns.insert(pattern.clone(), Regex::new(pattern)?);
+                        }
+                    }
+                    _ => collect_patterns(value, patterns)?,
+                }
+            }
+        }
+        Value::Array(array) => {
+            for value in array {
+                collect_patterns(value, patterns)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Violations found in the responses to a method
+#[derive(Debug, Default)]
+struct Violations {
+    checked: u64,
+    /// Responses to a method the spec does not define
+    unchecked: u64,
+    violations: u64,
+    example: Option<String>,
+}
+
+struct Validator {
+    spec: Spec,
+    methods: Mutex<BTreeMap<String, Violations>>,
+}
+
+/// Check every response received from now on against the bundled spec of `version`
+pub fn enable(version: &str) -> color_eyre::Result<()> {
+    let spec = Spec::bundled(version)?;
+
+    tracing::info!("Validating responses against RPC spec {}", spec.version());
+
+    if VALIDATOR
+        .set(Validator {
+            spec,
+            methods: Mutex::default(),
+        })
+        .is_err()
+    {
+        bail!("Response validation is already enabled");
+    }
+
+    Ok(())
+}
+
+/// Check a JSON-RPC response to `method` if validation is enabled, recording any violation
+pub fn validate_response(method: JsonRpcMethod, response: &Value) {
+    let Some(validator) = VALIDATOR.get() else {
+        return;
+    };
+
+    let method = method_name(method);
+    let result = validator
+        .spec
+        .has_method(&method)
+        .then(|| validator.spec.check_response(&method, response));
+
+    let mut methods = validator
+        .methods
+        .lock()
+        .expect("Spec violations lock is never poisoned");
+    let violations = methods.entry(method).or_default();
+
+    match result {
+        Some(Ok(())) => violations.checked += 1,
+        Some(Err(violation)) => {
+            violations.checked += 1;
+            violations.violations += 1;
+            violations.example.get_or_insert(violation);
+        }
+        None => violations.unchecked += 1,
+    }
+}
+
+/// Report of the violations found so far, per method
+pub fn report() -> Option<BenchmarkReport> {
+    let validator = VALIDATOR.get()?;
+    let methods = validator
+        .methods
+        .lock()
+        .expect("Spec violations lock is never poisoned");
+
+    let checked = methods
+        .values()
+        .map(|violations| violations.checked)
+        .sum::<u64>();
+    let mut report = BenchmarkReport::new(
+        format!("Spec {} Violations", validator.spec.version()),
+        checked as usize,
+    );
+
+    for (method, violations) in methods.iter() {
+        if violations.unchecked != 0 {
+            report.metrics.push(MetricResult {
+                name: format!("{method} Unchecked Responses").into(),
+                unit: "responses",
+                value: violations.unchecked.into(),
+            });
+        }
+
+        if violations.checked == 0 {
+            continue;
+        }
+
+        report.metrics.extend_from_slice(&[
+            MetricResult {
+                name: format!("{method} Checked Responses").into(),
+                unit: "responses",
+                value: violations.checked.into(),
+            },
+            MetricResult {
+                name: format!("{method} Violations").into(),
+                unit: "responses",
+                value: violations.violations.into(),
+            },
+        ]);
+
+        if let Some(example) = &violations.example {
+            report.metrics.push(MetricResult {
+                name: format!("{method} Violation Example").into(),
+                unit: "",
+                value: example.clone().into(),
+            });
+        }
+    }
+
+    Some(report)
+}
+
+/// Name of `method` as sent over JSON-RPC
+pub fn method_name(method: JsonRpcMethod) -> String {
+    match serde_json::to_value(method) {
+        Ok(Value::String(name)) => name,
+        _ => format!("{method:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const DOCUMENT: &str = "main.json";
+
+    /// Spec made of `documents` alone, without any method
+    fn spec(documents: Vec<(&'static str, Value)>) -> Spec {
+        let mut patterns = HashMap::new();
+        for (_, document) in &documents {
+            collect_patterns(document, &mut patterns).unwrap();
+        }
+
+        Spec {
+            version: "test",
+            documents: documents.into_iter().collect(),
+            methods: HashMap::new(),
+            patterns,
+        }
+    }
+
+    /// Check `valid` passes `schema` and `invalid` does not
+    fn assert_keyword(schema: Value, valid: Value, invalid: Value) {
+        let spec = spec(vec![(DOCUMENT, json!({ "schema": schema }))]);
+        let schema = &spec.documents[DOCUMENT]["schema"];
+
+        assert_eq!(spec.check(DOCUMENT, schema, &valid, "result"), Ok(()));
+        assert!(spec.check(DOCUMENT, schema, &invalid, "result").is_err());
+    }
+
+    #[test]
+    fn type_keyword() {
+        assert_keyword(json!({ "type": "integer" }), json!(1), json!("1"));
+        assert_keyword(json!({ "type": "string" }), json!("1"), json!(1));
+    }
+
+    #[test]
+    fn enum_keyword() {
+        assert_keyword(
+            json!({ "enum": ["ACCEPTED_ON_L2", "ACCEPTED_ON_L1"] }),
+            json!("ACCEPTED_ON_L2"),
+            json!("REJECTED"),
+        );
+    }
+
+    #[test]
+    fn const_keyword() {
+        assert_keyword(
+            json!({ "const": "INVOKE" }),
+            json!("INVOKE"),
+            json!("DECLARE"),
+        );
+    }
+
+    #[test]
+    fn pattern_keyword() {
+        assert_keyword(
+            json!({ "pattern": "^0x[0-9a-f]+$" }),
+            json!("0x1a"),
+            json!("1a"),
+        );
+    }
+
+    #[test]
+    fn minimum_keyword() {
+        assert_keyword(json!({ "minimum": 0 }), json!(0), json!(-1));
+    }
+
+    #[test]
+    fn required_keyword() {
+        assert_keyword(
+            json!({ "required": ["block_hash"] }),
+            json!({ "block_hash": "0x1" }),
+            json!({ "block_number": 1 }),
+        );
+    }
+
+    #[test]
+    fn properties_keyword() {
+        assert_keyword(
+            json!({ "properties": { "block_number": { "type": "integer" } } }),
+            json!({ "block_number": 1, "other": "field" }),
+            json!({ "block_number": "1" }),
+        );
+    }
+
+    #[test]
+    fn items_keyword() {
+        assert_keyword(
+            json!({ "items": { "type": "string" } }),
+            json!(["0x1", "0x2"]),
+            json!(["0x1", 2]),
+        );
+    }
+
+    #[test]
+    fn all_of_keyword() {
+        assert_keyword(
+            json!({ "allOf": [{ "required": ["a"] }, { "required": ["b"] }] }),
+            json!({ "a": 1, "b": 2 }),
+            json!({ "a": 1 }),
+        );
+    }
+
+    #[test]
+    fn one_of_and_any_of_keywords() {
+        for keyword in ["oneOf", "anyOf"] {
+            assert_keyword(
+                json!({ keyword: [{ "type": "integer" }, { "type": "null" }] }),
+                json!(null),
+                json!("1"),
+            );
+        }
+    }
+
+    #[test]
+    fn overlapping_one_of_variants() {
+        assert_keyword(
+            json!({ "oneOf": [{ "required": ["a"] }, { "required": ["b"] }] }),
+            json!({ "a": 1, "b": 2 }),
+            json!({ "c": 3 }),
+        );
+    }
+
+    #[test]
+    fn not_keyword() {
+        assert_keyword(
+            json!({ "not": { "required": ["error"] } }),
+            json!({ "result": 1 }),
+            json!({ "error": 1 }),
+        );
+    }
+
+    #[test]
+    fn cross_document_ref() {
+        let spec = spec(vec![
+            (
+                DOCUMENT,
+                json!({ "components": { "schemas": {
+                    "TXN_HASH": { "$ref": "./api/other.json#/components/schemas/TXN_HASH" }
+                } } }),
+            ),
+            (
+                "other.json",
+                json!({ "components": { "schemas": {
+                    "TXN_HASH": { "$ref": "#/components/schemas/FELT" },
+                    "FELT": { "type": "string", "pattern": "^0x[0-9a-f]+$" }
+                } } }),
+            ),
+        ]);
+        let schema = json!({ "$ref": "#/components/schemas/TXN_HASH" });
+
+        let (document, resolved) = spec.resolve(DOCUMENT, &schema).unwrap();
+        assert_eq!(document, "other.json");
+        assert_eq!(resolved["type"], "string");
+
+        assert_eq!(
+            spec.check(DOCUMENT, &schema, &json!("0x1"), "result"),
+            Ok(())
+        );
+        assert!(spec
+            .check(DOCUMENT, &schema, &json!("1"), "result")
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_ref() {
+        let spec = spec(vec![(DOCUMENT, json!({}))]);
+        let schema = json!({ "$ref": "./missing.json#/components/schemas/FELT" });
+
+        assert!(spec.resolve(DOCUMENT, &schema).is_err());
+    }
+
+    #[test]
+    fn bundled_responses() {
+        let spec = Spec::bundled("0.7.0").unwrap();
+        assert_eq!(spec.version(), "0.7.1");
+
+        let check = |method, mut response: Value| {
+            response["jsonrpc"] = json!("2.0");
+            response["id"] = json!(1);
+            spec.check_response(method, &response)
+        };
+
+        // The write API refers to the schemas of the main API
+        assert_eq!(
+            check(
+                "starknet_addInvokeTransaction",
+                json!({ "result": { "transaction_hash": "0x1" } })
+            ),
+            Ok(())
+        );
+        assert!(check(
+            "starknet_addInvokeTransaction",
+            json!({ "result": { "transaction_hash": "1" } })
+        )
+        .is_err());
+
+        assert_eq!(
+            check(
+                "starknet_blockNumber",
+                json!({ "error": { "code": 32, "message": "There are no blocks" } })
+            ),
+            Ok(())
+        );
+        assert!(check(
+            "starknet_blockNumber",
+            json!({ "error": { "code": 29, "message": "Transaction hash not found" } })
+        )
+        .is_err());
+
+        // Not part of 0.7, so its responses cannot be checked
+        assert!(spec.has_method("starknet_blockNumber"));
+        assert!(!spec.has_method("starknet_getStorageProof"));
+    }
+}
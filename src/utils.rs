@@ -8,8 +8,9 @@ use lazy_static::lazy_static;
 
 use starknet::core::types::Felt;
 use starknet::core::types::{
-    BlockId, BlockWithTxs, ExecutionResources, ExecutionResult, MaybePendingBlockWithTxs,
-    StarknetError,
+    BlockId, BlockWithTxs, EmittedEvent, Event, EventFilter, ExecutionResources, ExecutionResult,
+    FeePayment, MaybePendingBlockWithTxs, StarknetError, TransactionReceipt,
+    TransactionReceiptWithBlockInfo,
 };
 use starknet::providers::ProviderError;
 use starknet::providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider};
@@ -220,6 +221,116 @@ pub async fn get_blocks_with_txs(
     Ok(results)
 }
 
+/// Get the fee actually charged for a transaction from its receipt
+pub fn receipt_actual_fee(receipt: &TransactionReceipt) -> &FeePayment {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => &receipt.actual_fee,
+        TransactionReceipt::L1Handler(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Declare(receipt) => &receipt.actual_fee,
+        TransactionReceipt::Deploy(receipt) => &receipt.actual_fee,
+        TransactionReceipt::DeployAccount(receipt) => &receipt.actual_fee,
+    }
+}
+
+/// Split `amount` in the low and high 128 bits of the u256 taken by ERC20 entrypoints
+pub fn u256_limbs(amount: Felt) -> (Felt, Felt) {
+    let bytes = amount.to_bytes_be();
+
+    (
+        Felt::from_bytes_be_slice(&bytes[16..]),
+        Felt::from_bytes_be_slice(&bytes[..16]),
+    )
+}
+
+/// Get the events emitted by a transaction from its receipt
+pub fn receipt_events(receipt: &TransactionReceipt) -> &[Event] {
+    match receipt {
+        TransactionReceipt::Invoke(receipt) => &receipt.events,
+        TransactionReceipt::L1Handler(receipt) => &receipt.events,
+        TransactionReceipt::Declare(receipt) => &receipt.events,
+        TransactionReceipt::Deploy(receipt) => &receipt.events,
+        TransactionReceipt::DeployAccount(receipt) => &receipt.events,
+    }
+}
+
+/// Get the receipts of a list of transactions, in the same order
+pub async fn get_receipts(
+    starknet_rpc: &Arc<JsonRpcClient<HttpTransport>>,
+    transactions: &[Felt],
+) -> Result<Vec<TransactionReceiptWithBlockInfo>> {
+    const MAX_CONCURRENT: usize = 50;
+
+    let mut join_set = JoinSet::new();
+    let mut results = Vec::with_capacity(transactions.len());
+
+    for (index, &tx_hash) in transactions.iter().enumerate() {
+        // Make sure we don't hit dev server with too many requests
+        while join_set.len() >= MAX_CONCURRENT {
+            let next = join_set
+                .join_next()
+                .await
+                .ok_or_eyre("JoinSet should have items")???;
+
+            results.push(next);
+        }
+
+        let starknet_rpc = starknet_rpc.clone();
+
+        join_set.spawn(async move {
+            starknet_rpc
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map(|receipt| (index, receipt))
+                .map_err(|err| eyre!(err))
+        });
+    }
+
+    while let Some(next) = join_set.join_next().await {
+        results.push(next??)
+    }
+
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    Ok(results.into_iter().map(|(_, receipt)| receipt).collect())
+}
+
+/// Get all the events matching `address` and `keys` emitted from
+/// `start_block` to `end_block` (including both), following continuation tokens
+pub async fn get_events_in_range(
+    starknet_rpc: &JsonRpcClient<HttpTransport>,
+    start_block: u64,
+    end_block: u64,
+    address: Option<Felt>,
+    keys: Option<Vec<Vec<Felt>>>,
+) -> Result<Vec<EmittedEvent>> {
+    const CHUNK_SIZE: u64 = 1000;
+
+    let filter = EventFilter {
+        from_block: Some(BlockId::Number(start_block)),
+        to_block: Some(BlockId::Number(end_block)),
+        address,
+        keys,
+    };
+
+    let mut events = Vec::new();
+    let mut continuation_token = None;
+
+    loop {
+        let page = starknet_rpc
+            .get_events(filter.clone(), continuation_token, CHUNK_SIZE)
+            .await?;
+
+        events.extend(page.events);
+
+        match page.continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+
+    Ok(events)
+}
+
 /// Sanitize a string to be used as a filename by removing/replacing illegal chars
 pub fn sanitize_filename(input: &str) -> String {
     // Define a set of characters to replace or remove
@@ -248,3 +359,87 @@ pub fn sanitize_filename(input: &str) -> String {
 
     truncated.to_string()
 }
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any sequence of characters and `?` any one character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+
+    // Position of the last `*` in the pattern and where in the name it started matching
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::{glob_match, u256_limbs};
+
+    #[test]
+    fn u256_limbs_split_at_128_bits() {
+        assert_eq!(u256_limbs(Felt::from(42)), (Felt::from(42), Felt::ZERO));
+
+        let low = Felt::from(u128::MAX);
+        let high = Felt::from(3);
+        let amount = low + high * Felt::TWO.pow(128u32);
+
+        assert_eq!(u256_limbs(amount), (low, high));
+    }
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("*_account", "deploy_account"));
+        assert!(glob_match("get*events", "get_events"));
+        assert!(glob_match("get*", "get_events"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("*_account", "deploy_accounts"));
+        assert!(!glob_match("get*events", "get_event"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("mi?t", "mint"));
+        assert!(!glob_match("mi?t", "mit"));
+        assert!(!glob_match("mi?t", "minnt"));
+    }
+
+    #[test]
+    fn glob_empty_pattern() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "transfer"));
+    }
+
+    #[test]
+    fn glob_no_match() {
+        assert!(glob_match("transfer", "transfer"));
+        assert!(!glob_match("transfer", "mint"));
+        assert!(!glob_match("transfer", "transfers"));
+        assert!(!glob_match("Transfer", "transfer"));
+    }
+}